@@ -0,0 +1,163 @@
+// blue_noise.rs
+//
+// Blue-noise threshold textures generated with Ulichney's void-and-cluster method.
+
+use crate::rng::XorShift32;
+
+/// Standard deviation of the Gaussian energy filter, as suggested by Ulichney.
+const SIGMA: f32 = 1.5;
+/// Fraction of pixels set in the initial random pattern.
+const INITIAL_DENSITY: f32 = 0.1;
+
+/// Offsets beyond this distance contribute less than 1e-4 of the center weight and are skipped.
+const MAX_RADIUS: usize = 7;
+
+/// Gaussian-filtered density of the set pixels in a binary pattern, wrapped toroidally
+/// so the resulting texture tiles seamlessly.
+#[derive(Clone)]
+struct EnergyField {
+    size: usize,
+    radius: usize,
+    /// Filter response for offsets `-radius..=radius` in both directions, row-major.
+    kernel: Vec<f32>,
+    energy: Vec<f32>,
+}
+
+impl EnergyField {
+    fn new(size: usize) -> EnergyField {
+        // Keep the window narrower than the texture so no pixel is visited twice.
+        let radius = MAX_RADIUS.min((size - 1) / 2);
+        let span = 2 * radius + 1;
+        let mut kernel = vec![0.0; span * span];
+        for (i, k) in kernel.iter_mut().enumerate() {
+            let dx = (i % span) as f32 - radius as f32;
+            let dy = (i / span) as f32 - radius as f32;
+            *k = (-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp();
+        }
+        EnergyField { size, radius, kernel, energy: vec![0.0; size * size] }
+    }
+
+    /// Adds (`sign` = 1) or removes (`sign` = -1) the contribution of the pixel at `index`.
+    fn update(&mut self, index: usize, sign: f32) {
+        let size = self.size;
+        let span = 2 * self.radius + 1;
+        let (px, py) = (index % size, index / size);
+        for ky in 0..span {
+            let y = (py + size + ky - self.radius) % size;
+            for kx in 0..span {
+                let x = (px + size + kx - self.radius) % size;
+                self.energy[y * size + x] += sign * self.kernel[ky * span + kx];
+            }
+        }
+    }
+
+    /// The set pixel with the highest energy.
+    fn tightest_cluster(&self, pattern: &[bool]) -> usize {
+        let mut best = 0;
+        let mut best_energy = f32::NEG_INFINITY;
+        for (i, (&set, &e)) in pattern.iter().zip(&self.energy).enumerate() {
+            if set && e > best_energy {
+                best_energy = e;
+                best = i;
+            }
+        }
+        best
+    }
+
+    /// The unset pixel with the lowest energy.
+    fn largest_void(&self, pattern: &[bool]) -> usize {
+        let mut best = 0;
+        let mut best_energy = f32::INFINITY;
+        for (i, (&set, &e)) in pattern.iter().zip(&self.energy).enumerate() {
+            if !set && e < best_energy {
+                best_energy = e;
+                best = i;
+            }
+        }
+        best
+    }
+}
+
+/// Generates a `size`×`size` blue-noise rank texture: every value in `0..size * size` appears
+/// exactly once, row-major, and thresholding at any rank yields an evenly spread pattern.
+pub fn void_and_cluster(size: usize, seed: u32) -> Vec<u32> {
+    let n = size * size;
+    let mut rng = XorShift32::new(seed);
+    let ones = ((n as f32 * INITIAL_DENSITY) as usize).max(1);
+    let mut pattern = vec![false; n];
+    let mut field = EnergyField::new(size);
+    let mut placed = 0;
+    while placed < ones {
+        let i = rng.next_u32() as usize % n;
+        if !pattern[i] {
+            pattern[i] = true;
+            field.update(i, 1.0);
+            placed += 1;
+        }
+    }
+
+    // Relax the random pattern: move the tightest cluster into the largest void until the
+    // pixel just removed is itself the largest void.
+    for _ in 0..n {
+        let cluster = field.tightest_cluster(&pattern);
+        pattern[cluster] = false;
+        field.update(cluster, -1.0);
+        let void = field.largest_void(&pattern);
+        pattern[void] = true;
+        field.update(void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0u32; n];
+
+    // Phase 1: strip the prototype's tightest clusters, ranking them downward.
+    let mut stripped = pattern.clone();
+    let mut stripped_field = field.clone();
+    for rank in (0..ones).rev() {
+        let cluster = stripped_field.tightest_cluster(&stripped);
+        stripped[cluster] = false;
+        stripped_field.update(cluster, -1.0);
+        ranks[cluster] = rank as u32;
+    }
+
+    // Phases 2 and 3: fill the largest voids, ranking upward. Past the half-way point the
+    // tightest cluster of unset pixels is exactly the pixel with the lowest energy from the
+    // set ones (the filter sums to the same total everywhere), so one loop covers both.
+    for rank in ones..n {
+        let void = field.largest_void(&pattern);
+        pattern[void] = true;
+        field.update(void, 1.0);
+        ranks[void] = rank as u32;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_form_a_permutation() {
+        for (size, seed) in [(8, 0), (16, 7), (32, 42)] {
+            let mut ranks = void_and_cluster(size, seed);
+            ranks.sort_unstable();
+            assert!(ranks.iter().enumerate().all(|(i, &r)| r == i as u32), "size {size}, seed {seed}");
+        }
+    }
+
+    #[test]
+    fn low_ranks_are_spread_out() {
+        // Thresholding at a quarter of the ranks should give isolated dots, not clumps.
+        let size = 32;
+        let ranks = void_and_cluster(size, 1);
+        let on = |x: usize, y: usize| ranks[(y % size) * size + x % size] < (size * size / 4) as u32;
+        let adjacent = (0..size)
+            .flat_map(|y| (0..size).map(move |x| (x, y)))
+            .filter(|&(x, y)| on(x, y) && (on(x + 1, y) || on(x, y + 1)))
+            .count();
+        // A random 25% pattern has about 44% of its dots touching a right or lower neighbor.
+        assert!(adjacent < size * size / 4 / 5, "{adjacent} clustered dots");
+    }
+}
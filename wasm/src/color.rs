@@ -0,0 +1,333 @@
+// color.rs
+//
+// Color space conversions and the distance metrics used for palette matching.
+
+use once_cell::sync::Lazy;
+
+/// Decodes an sRGB-encoded channel (0–255) to linear light (0–1).
+#[inline(always)]
+pub fn srgb_to_linear(value: f32) -> f32 {
+    let v = (value / 255.0).clamp(0.0, 1.0);
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear-light channel (0–1) back to sRGB (0–255).
+#[inline(always)]
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let v = value.clamp(0.0, 1.0);
+    let encoded = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    encoded * 255.0
+}
+
+/// Lookup table form of [`srgb_to_linear`] for 8-bit channels.
+static SRGB_TO_LINEAR_LUT: Lazy<[f32; 256]> = Lazy::new(|| {
+    let mut lut = [0.0f32; 256];
+    for (i, entry) in lut.iter_mut().enumerate() {
+        *entry = srgb_to_linear(i as f32);
+    }
+    lut
+});
+
+/// Decodes an 8-bit sRGB channel to linear light (0–1).
+#[inline(always)]
+pub fn srgb_byte_to_linear(value: u8) -> f32 {
+    SRGB_TO_LINEAR_LUT[value as usize]
+}
+
+/// Converts sRGB (0–255 per channel) to CIELAB (D65 white point).
+pub fn srgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let r = srgb_to_linear(rgb[0]);
+    let g = srgb_to_linear(rgb[1]);
+    let b = srgb_to_linear(rgb[2]);
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Converts sRGB (0–255 per channel) to Björn Ottosson's OKLab.
+pub fn srgb_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let r = srgb_to_linear(rgb[0]);
+    let g = srgb_to_linear(rgb[1]);
+    let b = srgb_to_linear(rgb[2]);
+    let l = (0.412_221_47 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+/// CIEDE2000 color difference between two CIELAB colors.
+pub fn ciede2000(lab1: [f32; 3], lab2: [f32; 3]) -> f32 {
+    let [l1, a1, b1] = lab1;
+    let [l2, a2, b2] = lab2;
+    let pow25_7 = 25.0f32.powi(7);
+
+    let c_bar = ((a1 * a1 + b1 * b1).sqrt() + (a2 * a2 + b2 * b2).sqrt()) / 2.0;
+    let c_bar7 = c_bar.powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + pow25_7)).sqrt());
+    let a1p = (1.0 + g) * a1;
+    let a2p = (1.0 + g) * a2;
+    let c1p = a1p.hypot(b1);
+    let c2p = a2p.hypot(b2);
+    let hue = |b: f32, a: f32| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1p = hue(b1, a1p);
+    let h2p = hue(b2, a2p);
+
+    let delta_lp = l2 - l1;
+    let delta_cp = c2p - c1p;
+    let delta_hp = if c1p * c2p == 0.0 {
+        0.0
+    } else {
+        let d = h2p - h1p;
+        if d > 180.0 {
+            d - 360.0
+        } else if d < -180.0 {
+            d + 360.0
+        } else {
+            d
+        }
+    };
+    let delta_big_hp = 2.0 * (c1p * c2p).sqrt() * (delta_hp.to_radians() / 2.0).sin();
+
+    let l_bar_p = (l1 + l2) / 2.0;
+    let c_bar_p = (c1p + c2p) / 2.0;
+    let h_bar_p = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180.0 {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 360.0 {
+        (h1p + h2p + 360.0) / 2.0
+    } else {
+        (h1p + h2p - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_bar_p - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar_p).to_radians().cos()
+        + 0.32 * (3.0 * h_bar_p + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar_p - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar_p - 275.0) / 25.0).powi(2)).exp();
+    let c_bar_p7 = c_bar_p.powi(7);
+    let r_c = 2.0 * (c_bar_p7 / (c_bar_p7 + pow25_7)).sqrt();
+    let l_offset = (l_bar_p - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l_offset / (20.0 + l_offset).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_p;
+    let s_h = 1.0 + 0.015 * c_bar_p * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let dl = delta_lp / s_l;
+    let dc = delta_cp / s_c;
+    let dh = delta_big_hp / s_h;
+    (dl * dl + dc * dc + dh * dh + r_t * dc * dh).max(0.0).sqrt()
+}
+
+/// Rec.601 luma weights, also used to weight channel differences in [`ColorMetric::WeightedRgb`].
+const REC601_WEIGHTS: [f32; 3] = [0.299, 0.587, 0.114];
+
+/// How "closest palette color" is measured. Each metric also defines the working space in
+/// which pixels are compared and quantization error is diffused.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMetric {
+    /// Euclidean distance on sRGB values.
+    #[default]
+    Rgb,
+    /// Euclidean distance on sRGB values with each channel weighted by its luma contribution.
+    WeightedRgb,
+    /// CIE76 ΔE: Euclidean distance in CIELAB.
+    Cie76,
+    /// CIEDE2000 ΔE in CIELAB.
+    Ciede2000,
+    /// Euclidean distance in OKLab.
+    Oklab,
+}
+
+impl ColorMetric {
+    /// Parses a metric name; unknown names fall back to plain RGB.
+    pub fn from_name(name: &str) -> ColorMetric {
+        match name {
+            "weighted-rgb" => ColorMetric::WeightedRgb,
+            "cie76" | "lab" => ColorMetric::Cie76,
+            "ciede2000" => ColorMetric::Ciede2000,
+            "oklab" => ColorMetric::Oklab,
+            _ => ColorMetric::Rgb,
+        }
+    }
+
+    /// Converts an sRGB color (0–255 per channel) into this metric's working space.
+    #[inline(always)]
+    pub fn to_space(self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            ColorMetric::Rgb | ColorMetric::WeightedRgb => rgb,
+            ColorMetric::Cie76 | ColorMetric::Ciede2000 => srgb_to_lab(rgb),
+            ColorMetric::Oklab => srgb_to_oklab(rgb),
+        }
+    }
+
+    /// Distance between two colors already in this metric's working space. Only the
+    /// ordering matters, so squared distances are returned where that is cheaper.
+    #[inline(always)]
+    pub fn distance(self, a: [f32; 3], b: [f32; 3]) -> f32 {
+        let d0 = a[0] - b[0];
+        let d1 = a[1] - b[1];
+        let d2 = a[2] - b[2];
+        match self {
+            ColorMetric::Rgb | ColorMetric::Cie76 | ColorMetric::Oklab => d0 * d0 + d1 * d1 + d2 * d2,
+            ColorMetric::WeightedRgb => {
+                let [wr, wg, wb] = REC601_WEIGHTS;
+                wr * d0 * d0 + wg * d1 * d1 + wb * d2 * d2
+            }
+            ColorMetric::Ciede2000 => ciede2000(a, b),
+        }
+    }
+
+    /// Squared distance between two colors in this metric's working space, for sums where
+    /// distances of every metric must be on the same scale.
+    #[inline(always)]
+    pub fn squared_distance(self, a: [f32; 3], b: [f32; 3]) -> f32 {
+        match self {
+            ColorMetric::Ciede2000 => ciede2000(a, b).powi(2),
+            _ => self.distance(a, b),
+        }
+    }
+}
+
+/// How RGB is collapsed to a single gray value before dithering.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LumaModel {
+    /// ITU-R BT.601 (SD video, JPEG): 0.299 R + 0.587 G + 0.114 B.
+    #[default]
+    Rec601,
+    /// ITU-R BT.709 (HD video, sRGB primaries): 0.2126 R + 0.7152 G + 0.0722 B.
+    Rec709,
+    /// ITU-R BT.2020 (UHD/HDR): 0.2627 R + 0.6780 G + 0.0593 B.
+    Rec2020,
+    /// Plain mean of the three channels.
+    Average,
+    /// Brightest channel (HSV value).
+    MaxChannel,
+    /// User-supplied R, G, B weights, normalized to sum to 1.
+    Custom([f32; 3]),
+}
+
+impl LumaModel {
+    /// Parses a model name. If `weights` holds at least three values they are used as a
+    /// custom model regardless of the name; unknown names fall back to Rec.601.
+    pub fn from_name(name: &str, weights: Option<&[f32]>) -> LumaModel {
+        if let Some(&[r, g, b, ..]) = weights {
+            let sum = r + g + b;
+            if sum.is_finite() && sum > 0.0 {
+                return LumaModel::Custom([r / sum, g / sum, b / sum]);
+            }
+        }
+        match name {
+            "rec709" => LumaModel::Rec709,
+            "rec2020" => LumaModel::Rec2020,
+            "average" => LumaModel::Average,
+            "max" => LumaModel::MaxChannel,
+            _ => LumaModel::Rec601,
+        }
+    }
+
+    /// Combines three channels (in any common scale) into a gray value on the same scale.
+    #[inline(always)]
+    pub fn apply(self, r: f32, g: f32, b: f32) -> f32 {
+        let [wr, wg, wb] = match self {
+            LumaModel::Rec601 => REC601_WEIGHTS,
+            LumaModel::Rec709 => [0.2126, 0.7152, 0.0722],
+            LumaModel::Rec2020 => [0.2627, 0.6780, 0.0593],
+            LumaModel::Average => [1.0 / 3.0; 3],
+            LumaModel::MaxChannel => return r.max(g).max(b),
+            LumaModel::Custom(weights) => weights,
+        };
+        wr * r + wg * g + wb * b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test pairs and expected ΔE00 from Sharma, Wu and Dalal, "The CIEDE2000 Color-Difference
+    /// Formula: Implementation Notes, Supplementary Test Data, and Mathematical Observations".
+    const SHARMA_PAIRS: [([f32; 3], [f32; 3], f32); 34] = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+        ([50.0, 2.8361, -74.0200], [50.0, 0.0, -82.7485], 3.4412),
+        ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -1.1848, -84.8006], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -0.9009, -85.5211], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, -1.0, 2.0], [50.0, 0.0, 0.0], 2.3669),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0009], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0010], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0011], 7.2195),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0012], 7.2195),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0009, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0010, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0011, -2.4900], 4.7461),
+        ([50.0, 2.5000, 0.0], [50.0, 0.0, -2.5000], 4.3065),
+        ([50.0, 2.5000, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5000, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5000, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5000, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5000, 0.0], [50.0, 3.1736, 0.5854], 1.0000),
+        ([50.0, 2.5000, 0.0], [50.0, 3.2972, 0.0], 1.0000),
+        ([50.0, 2.5000, 0.0], [50.0, 1.8634, 0.5757], 1.0000),
+        ([50.0, 2.5000, 0.0], [50.0, 3.2592, 0.3350], 1.0000),
+        ([60.2574, -34.0099, 36.2677], [60.4626, -34.1751, 39.4387], 1.2644),
+        ([63.0109, -31.0961, -5.8663], [62.8187, -29.7946, -4.0864], 1.2630),
+        ([61.2901, 3.7196, -5.3901], [61.4292, 2.2480, -4.9620], 1.8731),
+        ([35.0831, -44.1164, 3.7933], [35.0232, -40.0716, 1.5901], 1.8645),
+        ([22.7233, 20.0904, -46.6940], [23.0331, 14.9730, -42.5619], 2.0373),
+        ([36.4612, 47.8580, 18.3852], [36.2715, 50.5065, 21.2231], 1.4146),
+        ([90.8027, -2.0831, 1.4410], [91.1528, -1.6435, 0.0447], 1.4441),
+        ([90.9257, -0.5406, -0.9208], [88.6381, -0.8985, -0.7239], 1.5381),
+        ([6.7747, -0.2908, -2.4247], [5.8714, -0.0985, -2.2286], 0.6377),
+        ([2.0776, 0.0795, -1.1350], [0.9033, -0.0636, -0.5514], 0.9082),
+    ];
+
+    #[test]
+    fn ciede2000_matches_sharma_reference_data() {
+        for (lab1, lab2, expected) in SHARMA_PAIRS {
+            let forward = ciede2000(lab1, lab2);
+            let backward = ciede2000(lab2, lab1);
+            assert!((forward - expected).abs() < 1e-4, "{lab1:?} {lab2:?}: {forward}, expected {expected}");
+            assert!((backward - expected).abs() < 1e-4, "{lab2:?} {lab1:?}: {backward}, expected {expected}");
+        }
+    }
+
+    #[test]
+    fn custom_luma_weights_are_normalized_and_override_the_name() {
+        let luma = LumaModel::from_name("rec709", Some(&[2.0, 1.0, 1.0]));
+        assert_eq!(luma, LumaModel::Custom([0.5, 0.25, 0.25]));
+        assert_eq!(luma.apply(255.0, 255.0, 255.0), 255.0);
+        // Weights that cannot be normalized fall back to the named model.
+        assert_eq!(LumaModel::from_name("rec709", Some(&[0.0, 0.0, 0.0])), LumaModel::Rec709);
+        assert_eq!(LumaModel::from_name("unknown", None), LumaModel::Rec601);
+    }
+}
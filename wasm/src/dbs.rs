@@ -0,0 +1,203 @@
+// dbs.rs
+//
+// Direct binary search (DBS): iteratively refines a binary halftone by toggling single
+// pixels and swapping neighbors whenever that lowers the error perceived through a model
+// of the human visual system.
+
+/// Standard deviation, in pixels, of the Gaussian standing in for the eye's point spread
+/// function at a typical viewing distance.
+const HVS_SIGMA: f32 = 1.5;
+
+/// Autocorrelation of the (separable) HVS filter. The 2-D autocorrelation at `(dx, dy)` is
+/// `taps[dx] * taps[dy]`, for offsets up to `radius` in each direction.
+struct Autocorrelation {
+    radius: isize,
+    taps: Vec<f32>,
+}
+
+impl Autocorrelation {
+    fn new() -> Autocorrelation {
+        let filter_radius = (3.0 * HVS_SIGMA).ceil() as isize;
+        let filter: Vec<f32> = (-filter_radius..=filter_radius)
+            .map(|x| (-(x * x) as f32 / (2.0 * HVS_SIGMA * HVS_SIGMA)).exp())
+            .collect();
+        let sum: f32 = filter.iter().sum();
+        let filter: Vec<f32> = filter.iter().map(|v| v / sum).collect();
+        let radius = 2 * filter_radius;
+        let taps = (-radius..=radius)
+            .map(|d| {
+                filter
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, &v)| filter.get((k as isize + d) as usize).map(|&u| u * v))
+                    .sum()
+            })
+            .collect();
+        Autocorrelation { radius, taps }
+    }
+
+    #[inline(always)]
+    fn tap(&self, d: isize) -> f32 {
+        if d.abs() > self.radius {
+            0.0
+        } else {
+            self.taps[(d + self.radius) as usize]
+        }
+    }
+
+    #[inline(always)]
+    fn at(&self, dx: isize, dy: isize) -> f32 {
+        self.tap(dx) * self.tap(dy)
+    }
+}
+
+/// Filters `values` with the autocorrelation, treating everything outside the image as 0.
+fn correlate(values: &[f32], w: usize, h: usize, c: &Autocorrelation) -> Vec<f32> {
+    let r = c.radius;
+    let mut rows = vec![0.0; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = 0.0;
+            for d in -r..=r {
+                let nx = x as isize + d;
+                if nx >= 0 && nx < w as isize {
+                    sum += values[y * w + nx as usize] * c.tap(d);
+                }
+            }
+            rows[y * w + x] = sum;
+        }
+    }
+    let mut out = vec![0.0; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = 0.0;
+            for d in -r..=r {
+                let ny = y as isize + d;
+                if ny >= 0 && ny < h as isize {
+                    sum += rows[ny as usize * w + x] * c.tap(d);
+                }
+            }
+            out[y * w + x] = sum;
+        }
+    }
+    out
+}
+
+/// Adds `amount` times the autocorrelation centered on `(x, y)` to `field`.
+fn add_autocorrelation(field: &mut [f32], w: usize, h: usize, x: usize, y: usize, amount: f32, c: &Autocorrelation) {
+    let r = c.radius;
+    let (x0, x1) = ((x as isize - r).max(0) as usize, (x as isize + r).min(w as isize - 1) as usize);
+    let (y0, y1) = ((y as isize - r).max(0) as usize, (y as isize + r).min(h as isize - 1) as usize);
+    for ny in y0..=y1 {
+        let row_weight = amount * c.tap(ny as isize - y as isize);
+        for nx in x0..=x1 {
+            field[ny * w + nx] += row_weight * c.tap(nx as isize - x as isize);
+        }
+    }
+}
+
+/// Refines `halftone` (one `bool` per pixel, `true` = white) towards the continuous-tone
+/// `gray` (0–1). Each pass visits every pixel once and applies the toggle or neighbor swap
+/// that lowers the perceived error the most. `progress(passes_done, max_iterations)` is
+/// called after every pass, including the last; the search stops after `max_iterations`
+/// passes, after a pass without changes, or when `progress` returns `false`.
+/// Returns the number of passes run.
+pub fn direct_binary_search(
+    gray: &[f32],
+    halftone: &mut [bool],
+    w: usize,
+    h: usize,
+    max_iterations: u32,
+    mut progress: impl FnMut(u32, u32) -> bool,
+) -> u32 {
+    let c = Autocorrelation::new();
+    let c0 = c.at(0, 0);
+    let error: Vec<f32> = halftone
+        .iter()
+        .zip(gray)
+        .map(|(&white, &g)| if white { 1.0 } else { 0.0 } - g)
+        .collect();
+    // Cross-correlation between the error and the HVS autocorrelation; the change in
+    // perceived error for any toggle or swap can be read off it directly.
+    let mut cross = correlate(&error, w, h, &c);
+
+    let mut passes = 0;
+    while passes < max_iterations {
+        let mut changes = 0;
+        for y in 0..h {
+            for x in 0..w {
+                let m = y * w + x;
+                // +1 turns the pixel white, -1 turns it black.
+                let a_m = if halftone[m] { -1.0 } else { 1.0 };
+                let mut best_delta = c0 + 2.0 * a_m * cross[m];
+                let mut best_swap = None;
+                for dy in -1isize..=1 {
+                    for dx in -1isize..=1 {
+                        let nx = x as isize + dx;
+                        let ny = y as isize + dy;
+                        if (dx == 0 && dy == 0) || nx < 0 || ny < 0 || nx >= w as isize || ny >= h as isize {
+                            continue;
+                        }
+                        let n = ny as usize * w + nx as usize;
+                        if halftone[n] == halftone[m] {
+                            continue;
+                        }
+                        let a_n = -a_m;
+                        let delta = 2.0 * c0 + 2.0 * a_m * a_n * c.at(dx, dy)
+                            + 2.0 * a_m * cross[m]
+                            + 2.0 * a_n * cross[n];
+                        if delta < best_delta {
+                            best_delta = delta;
+                            best_swap = Some((nx as usize, ny as usize));
+                        }
+                    }
+                }
+                if best_delta < -1e-7 {
+                    halftone[m] = !halftone[m];
+                    add_autocorrelation(&mut cross, w, h, x, y, a_m, &c);
+                    if let Some((nx, ny)) = best_swap {
+                        halftone[ny * w + nx] = !halftone[ny * w + nx];
+                        add_autocorrelation(&mut cross, w, h, nx, ny, -a_m, &c);
+                    }
+                    changes += 1;
+                }
+            }
+        }
+        passes += 1;
+        if !progress(passes, max_iterations) || changes == 0 {
+            break;
+        }
+    }
+    passes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refining_a_thresholded_gray_recovers_its_tone() {
+        let (w, h) = (24, 24);
+        for level in [0.25, 0.5, 0.8] {
+            let gray = vec![level; w * h];
+            // Plain thresholding turns the whole patch black or white.
+            let mut halftone = vec![level >= 0.5; w * h];
+            let mut reported = 0;
+            let passes = direct_binary_search(&gray, &mut halftone, w, h, 20, |done, _| {
+                reported = done;
+                true
+            });
+            assert!(passes > 1);
+            assert_eq!(reported, passes, "progress misses the last pass");
+            let white = halftone.iter().filter(|&&white| white).count() as f32 / (w * h) as f32;
+            assert!((white - level).abs() < 0.05, "{level}: {white}");
+        }
+    }
+
+    #[test]
+    fn progress_can_stop_the_search() {
+        let gray = vec![0.5; 16 * 16];
+        let mut halftone = vec![false; 16 * 16];
+        assert_eq!(direct_binary_search(&gray, &mut halftone, 16, 16, 20, |_, _| false), 1);
+    }
+}
@@ -0,0 +1,253 @@
+// halftone.rs
+//
+// Clustered-dot (AM) halftone screens: a rotated grid of cells whose thresholds grow a
+// dot outward from each cell center, like a newspaper screen. Also line screens, whose
+// strokes widen with the tone like an engraving.
+
+/// Largest halftone cell or line spacing, in pixels. The cell's thresholds are precomputed
+/// and sorted, so an unbounded size would let one parameter allocate without limit.
+const MAX_CELL_SIZE: f32 = 256.0;
+
+/// Minor-to-major axis ratio of elliptical dots.
+const ELLIPSE_ASPECT: f32 = 0.7;
+
+/// Weight of the secondary term that orders otherwise tied cell positions, so square dots
+/// and lines grow from their middle rather than in scan order.
+const TIE_BREAK: f32 = 1e-3;
+
+/// Limits a cell size or line spacing to 2–[`MAX_CELL_SIZE`] pixels; NaN becomes the
+/// smallest cell.
+fn clamp_cell_size(size: f32) -> f32 {
+    if size >= 2.0 { size.min(MAX_CELL_SIZE) } else { 2.0 }
+}
+
+/// Shape of the dots grown in each halftone cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DotShape {
+    /// Circular dots that join into a checkerboard at 50% and invert beyond it.
+    #[default]
+    Round,
+    /// Elongated dots that chain along the screen angle before the round ones would touch.
+    Elliptical,
+    /// Square dots.
+    Square,
+    /// Parallel lines running along the screen angle.
+    Line,
+}
+
+impl DotShape {
+    /// Parses a dot shape name; unknown names fall back to round dots.
+    pub fn from_name(name: &str) -> DotShape {
+        match name {
+            "elliptical" | "ellipse" => DotShape::Elliptical,
+            "square" => DotShape::Square,
+            "line" => DotShape::Line,
+            _ => DotShape::Round,
+        }
+    }
+
+    /// Spot function: cell positions with lower values turn dark first. `u` and `v` run
+    /// from -1 to 1 across the cell, with the dot center at the origin. Round dots use the
+    /// Euclidean spot function: a circle inside the diamond |u| + |v| ≤ 1, which fills
+    /// exactly half the cell, and a shrinking white circle around the cell corners outside
+    /// it, so neighboring dots meet in a checkerboard at 50%.
+    fn spot(self, u: f32, v: f32) -> f32 {
+        match self {
+            DotShape::Round if u.abs() + v.abs() <= 1.0 => u * u + v * v,
+            // Offset past the largest value inside the diamond, keeping the order monotonic.
+            DotShape::Round => 2.0 - ((1.0 - u.abs()).powi(2) + (1.0 - v.abs()).powi(2)),
+            DotShape::Elliptical => u * u + (v * v) / (ELLIPSE_ASPECT * ELLIPSE_ASPECT),
+            DotShape::Square => u.abs().max(v.abs()) + TIE_BREAK * (u * u + v * v),
+            DotShape::Line => v.abs() + TIE_BREAK * u.abs(),
+        }
+    }
+}
+
+/// A halftone screen of square cells `cell_size` pixels wide, rotated by `angle` degrees.
+pub struct Screen {
+    cos: f32,
+    sin: f32,
+    inv_cell: f32,
+    /// Samples per cell side.
+    resolution: usize,
+    /// Threshold in (0, 1) for each sample of one cell, row-major.
+    cell: Vec<f32>,
+}
+
+impl Screen {
+    pub fn new(shape: DotShape, cell_size: f32, angle: f32) -> Screen {
+        // One sample per pixel: an unrotated screen then hits every threshold exactly once per
+        // cell, and rotated ones sample the cell close to uniformly.
+        let cell_size = clamp_cell_size(cell_size);
+        let n = cell_size.round() as usize;
+        let coord = |i: usize| (i as f32 + 0.5) / n as f32 * 2.0 - 1.0;
+        let spot: Vec<f32> = (0..n * n).map(|i| shape.spot(coord(i % n), coord(i / n))).collect();
+        // Thresholds are the rank of each sample's spot value, so a gray level darkens exactly
+        // that fraction of the cell whatever the dot shape.
+        let mut order: Vec<usize> = (0..n * n).collect();
+        order.sort_by(|&a, &b| spot[a].total_cmp(&spot[b]).then(a.cmp(&b)));
+        let mut cell = vec![0.0; n * n];
+        for (rank, &i) in order.iter().enumerate() {
+            cell[i] = 1.0 - (rank as f32 + 0.5) / (n * n) as f32;
+        }
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Screen { cos, sin, inv_cell: 1.0 / cell_size, resolution: n, cell }
+    }
+
+    /// Threshold in (0, 1) for the pixel at `(x, y)`, sampled at its center.
+    #[inline(always)]
+    pub fn threshold(&self, x: usize, y: usize) -> f32 {
+        let n = self.resolution;
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let u = (px * self.cos + py * self.sin) * self.inv_cell;
+        let v = (py * self.cos - px * self.sin) * self.inv_cell;
+        let cu = (((u - u.floor()) * n as f32) as usize).min(n - 1);
+        let cv = (((v - v.floor()) * n as f32) as usize).min(n - 1);
+        self.cell[cv * n + cu]
+    }
+}
+
+// === Line Screens ===
+
+/// Amplitude of wavy lines, in line spacings.
+const WAVE_AMPLITUDE: f32 = 0.35;
+
+/// Wavelength of wavy lines, in line spacings.
+const WAVE_LENGTH: f32 = 4.0;
+
+/// Engraving-style line patterns whose strokes thicken as the tone darkens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinePattern {
+    /// Parallel straight lines running along the screen angle.
+    Straight,
+    /// Parallel lines undulating along the screen angle.
+    Wavy,
+    /// Concentric circles around the image center.
+    Circles,
+    /// Two sets of straight lines crossing at right angles.
+    Crosshatch,
+}
+
+/// A line screen with lines `spacing` pixels apart, rotated by `angle` degrees about
+/// `center`.
+pub struct LineScreen {
+    pattern: LinePattern,
+    cos: f32,
+    sin: f32,
+    inv_spacing: f32,
+    center: (f32, f32),
+}
+
+impl LineScreen {
+    pub fn new(pattern: LinePattern, spacing: f32, angle: f32, center: (f32, f32)) -> LineScreen {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        LineScreen { pattern, cos, sin, inv_spacing: 1.0 / clamp_cell_size(spacing), center }
+    }
+
+    /// Threshold in [0, 1] for the pixel at `(x, y)`.
+    #[inline(always)]
+    pub fn threshold(&self, x: usize, y: usize) -> f32 {
+        // Sampling a quarter pixel off center keeps the pixels on either side of an
+        // axis-aligned stroke from sharing a threshold, so strokes widen one pixel at a time.
+        let (px, py) = (x as f32 + 0.25 - self.center.0, y as f32 + 0.25 - self.center.1);
+        let u = (px * self.cos + py * self.sin) * self.inv_spacing;
+        let v = (py * self.cos - px * self.sin) * self.inv_spacing;
+        match self.pattern {
+            LinePattern::Straight => stroke(v),
+            LinePattern::Wavy => stroke(v + WAVE_AMPLITUDE * (u * std::f32::consts::TAU / WAVE_LENGTH).sin()),
+            LinePattern::Circles => stroke(u.hypot(v)),
+            // A pixel stays white only while it is off both strokes; squaring the larger
+            // threshold makes that happen for exactly the gray level's share of pixels. The
+            // smaller one spreads out the pixels that tie on it, which otherwise come in ever
+            // larger groups along axis-aligned strokes and skew the tone.
+            LinePattern::Crosshatch => {
+                let (a, b) = (stroke(u), stroke(v));
+                let (high, low) = (a.max(b), a.min(b));
+                (high * high + (2.0 * low - high) * self.inv_spacing).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// Threshold across one line period: highest on the stroke's center line and falling
+/// linearly to the gap between strokes, so a gray level darkens that fraction of the period.
+#[inline(always)]
+fn stroke(v: f32) -> f32 {
+    1.0 - ((v - v.floor()) - 0.5).abs() * 2.0
+}
+
+// === CMYK Separation ===
+
+/// Conventional screen angles in degrees for the C, M, Y and K separations.
+pub const CMYK_ANGLES: [f32; 4] = [15.0, 75.0, 0.0, 45.0];
+
+/// Splits an RGB reflectance (0–1 per channel) into C, M, Y and K ink coverage (0–1).
+/// `black_generation` is the share of the common gray component printed in black (GCR);
+/// `under_color_removal` is the share of that black taken back out of C, M and Y (UCR).
+pub fn rgb_to_cmyk(rgb: [f32; 3], black_generation: f32, under_color_removal: f32) -> [f32; 4] {
+    let [c, m, y] = rgb.map(|v| 1.0 - v.clamp(0.0, 1.0));
+    let k = c.min(m).min(y) * black_generation.clamp(0.0, 1.0);
+    let removed = k * under_color_removal.clamp(0.0, 1.0);
+    // Overprinted inks multiply, so the remaining color is rescaled to what shows through
+    // the black rather than simply reduced by it.
+    let scale = if removed < 1.0 { 1.0 / (1.0 - removed) } else { 0.0 };
+    [(c - removed) * scale, (m - removed) * scale, (y - removed) * scale, k]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_shape_keeps_the_gray_level_of_a_cell() {
+        for shape in [DotShape::Round, DotShape::Elliptical, DotShape::Square, DotShape::Line] {
+            let screen = Screen::new(shape, 8.0, 0.0);
+            for level in [0.25, 0.5, 0.75] {
+                let white = (0..64).filter(|&i| screen.threshold(i % 8, i / 8) < level).count();
+                assert_eq!(white, (64.0 * level) as usize, "{shape:?} at {level}");
+            }
+        }
+    }
+
+    #[test]
+    fn dots_grow_from_the_cell_center() {
+        let screen = Screen::new(DotShape::Round, 8.0, 0.0);
+        assert!(screen.threshold(3, 4) > screen.threshold(0, 0));
+        assert!(screen.threshold(4, 3) > screen.threshold(7, 1));
+    }
+
+    #[test]
+    fn round_dots_meet_in_a_checkerboard_at_half_tone() {
+        // At 50% the dot reaches the middle of every cell edge, leaving the corners white.
+        let screen = Screen::new(DotShape::Round, 9.0, 0.0);
+        let white = |x, y| screen.threshold(x, y) < 0.5;
+        for (x, y) in [(0, 4), (8, 4), (4, 0), (4, 8)] {
+            assert!(!white(x, y), "edge middle ({x}, {y})");
+        }
+        for (x, y) in [(0, 0), (8, 0), (0, 8), (8, 8)] {
+            assert!(white(x, y), "corner ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn separations_with_full_ucr_overprint_to_the_original_color() {
+        for rgb in [[0.2, 0.5, 0.9], [1.0, 1.0, 1.0], [0.3, 0.3, 0.3], [0.0, 0.0, 0.0]] {
+            for gcr in [0.0, 0.5, 1.0] {
+                let [c, m, y, k] = rgb_to_cmyk(rgb, gcr, 1.0);
+                let printed = [c, m, y].map(|ink| (1.0 - ink) * (1.0 - k));
+                for (p, v) in printed.iter().zip(rgb) {
+                    assert!((p - v).abs() < 1e-5, "{rgb:?} with GCR {gcr}: {printed:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn straight_lines_run_along_the_screen_angle() {
+        let screen = LineScreen::new(LinePattern::Straight, 8.0, 0.0, (0.0, 0.0));
+        for y in 0..16 {
+            assert!((0..16).all(|x| screen.threshold(x, y) == screen.threshold(0, y)), "row {y}");
+        }
+        assert!(screen.threshold(0, 4) > screen.threshold(0, 0));
+    }
+}
@@ -1,10 +1,27 @@
 // lib.rs
+
+mod blue_noise;
+mod color;
+mod dbs;
+mod halftone;
+mod palette;
+mod pattern;
+mod quantize;
+mod rng;
+mod stipple;
+
 use wasm_bindgen::prelude::*;
 use rayon::prelude::*;
-use std::mem::size_of;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
 use once_cell::sync::Lazy;
 
+pub use color::{ColorMetric, LumaModel};
+pub use halftone::{DotShape, LinePattern};
+use color::{linear_to_srgb, srgb_byte_to_linear, srgb_to_linear};
+use rng::XorShift32;
+pub use palette::{Palette, PALETTE_REGISTRY};
+
 // === Utility Functions ===
 
 #[inline(always)]
@@ -70,10 +87,10 @@ fn apply_preprocessing(
         // Compute the pointer once before entering the parallel loop.
         let data_ptr = data.as_mut_ptr() as usize;
         (0..h).into_par_iter().for_each(|y| {
-            let y0 = if y >= radius { y - radius } else { 0 };
+            let y0 = y.saturating_sub(radius);
             let y1 = (y + radius).min(h - 1);
             for x in 0..w {
-                let x0 = if x >= radius { x - radius } else { 0 };
+                let x0 = x.saturating_sub(radius);
                 let x1 = (x + radius).min(w - 1);
                 let area = ((x1 - x0 + 1) * (y1 - y0 + 1)) as u32;
                 let sum_r = rect_sum(&int_r, w, x0, y0, x1, y1);
@@ -131,12 +148,12 @@ fn apply_preprocessing(
     // --- Contrast and Gamma Correction Stage ---
     let contrast_factor = contrast / 100.0;
     let mut lut = [0u8; 256];
-    for i in 0..256 {
+    for (i, entry) in lut.iter_mut().enumerate() {
         let pixel = i as f32;
         let contrasted = ((pixel - 128.0) * contrast_factor) + 128.0;
         let normalized = clamp(contrasted / 255.0, 0.0, 1.0);
         let corrected = 255.0 * normalized.powf(1.0 / gamma);
-        lut[i] = clamp(corrected, 0.0, 255.0) as u8;
+        *entry = clamp(corrected, 0.0, 255.0) as u8;
     }
     data.par_chunks_mut(4).for_each(|chunk| {
         // Only adjust RGB channels; leave alpha untouched.
@@ -146,6 +163,111 @@ fn apply_preprocessing(
     });
 }
 
+// === Grayscale Helpers ===
+
+/// Luminance of an 8-bit RGB pixel on a 0–255 scale using `luma`. In linear-light mode the
+/// channels are decoded from sRGB first, so the result is proportional to emitted light.
+#[inline(always)]
+fn luminance(r: u8, g: u8, b: u8, luma: LumaModel, linear_light: bool) -> f32 {
+    if linear_light {
+        255.0 * luma.apply(srgb_byte_to_linear(r), srgb_byte_to_linear(g), srgb_byte_to_linear(b))
+    } else {
+        luma.apply(r as f32, g as f32, b as f32)
+    }
+}
+
+/// Maps the user-facing (sRGB) threshold into the domain the dither loops work in.
+fn working_threshold(threshold: u8, linear_light: bool) -> f32 {
+    if linear_light {
+        255.0 * srgb_byte_to_linear(threshold)
+    } else {
+        threshold as f32
+    }
+}
+
+/// Snaps a working-domain gray value to one of `levels` evenly spaced gray levels.
+/// `threshold` (0–255) sets where between two adjacent levels the output switches to the
+/// upper one; with two levels this is the classic `value < threshold ? 0 : 255`.
+#[inline(always)]
+fn quantize_gray(value: f32, threshold: f32, levels: u32) -> f32 {
+    let steps = (levels.clamp(2, 256) - 1) as f32;
+    let step = 255.0 / steps;
+    let lower = (value / step).floor().clamp(0.0, steps - 1.0);
+    if value < (lower + threshold / 255.0) * step {
+        lower * step
+    } else {
+        (lower + 1.0) * step
+    }
+}
+
+/// Converts a working-domain gray value back to an sRGB byte.
+#[inline(always)]
+fn encode_gray(value: f32, linear_light: bool) -> u8 {
+    let value = value.clamp(0.0, 255.0);
+    if linear_light {
+        linear_to_srgb(value / 255.0).round() as u8
+    } else {
+        value.round() as u8
+    }
+}
+
+/// Per-pixel luminance of an RGBA buffer.
+fn grayscale(data: &[u8], luma: LumaModel, linear_light: bool) -> Vec<f32> {
+    data.par_chunks_exact(4)
+        .map(|chunk| luminance(chunk[0], chunk[1], chunk[2], luma, linear_light))
+        .collect()
+}
+
+/// Averages each `bs`×`bs` block of a grayscale buffer.
+fn downsample_gray(gray: &[f32], w: usize, h: usize, bs: usize) -> Vec<f32> {
+    if w == 0 || h == 0 {
+        return Vec::new();
+    }
+    let new_w = w.div_ceil(bs);
+    let new_h = h.div_ceil(bs);
+    let mut down_gray = vec![0.0; new_w * new_h];
+    down_gray.par_chunks_mut(new_w).enumerate().for_each(|(ny, row)| {
+        for (nx, out) in row.iter_mut().enumerate() {
+            let mut sum = 0.0;
+            let mut count = 0;
+            let y_end = ((ny + 1) * bs).min(h);
+            let x_end = ((nx + 1) * bs).min(w);
+            for y in ny * bs..y_end {
+                for x in nx * bs..x_end {
+                    sum += gray[y * w + x];
+                    count += 1;
+                }
+            }
+            *out = sum / (count as f32);
+        }
+    });
+    down_gray
+}
+
+/// Writes a grayscale buffer (downsampled by `bs`) back into the RGB channels of `data`.
+fn write_gray(data: &mut [u8], gray: &[f32], w: usize, h: usize, bs: usize, linear_light: bool) {
+    let new_w = w.div_ceil(bs);
+    let data_ptr = data.as_mut_ptr() as usize;
+    (0..h.div_ceil(bs)).into_par_iter().for_each(|ny| {
+        for nx in 0..new_w {
+            let val = encode_gray(gray[ny * new_w + nx], linear_light);
+            let y_end = ((ny + 1) * bs).min(h);
+            let x_end = ((nx + 1) * bs).min(w);
+            for y in ny * bs..y_end {
+                for x in nx * bs..x_end {
+                    let idx = (y * w + x) * 4;
+                    unsafe {
+                        let ptr = (data_ptr + idx) as *mut u8;
+                        *ptr = val;
+                        *ptr.add(1) = val;
+                        *ptr.add(2) = val;
+                    }
+                }
+            }
+        }
+    });
+}
+
 // === Dither Parameters and Trait ===
 
 pub struct DitherParams {
@@ -157,6 +279,46 @@ pub struct DitherParams {
     pub block_scale: u32,
     pub bayer_width: u8,
     pub bayer_height: u8,
+    /// When set, output is quantized to these colors instead of black and white.
+    pub palette: Option<Palette>,
+    /// Dither in linear light: sRGB input is decoded before quantization and the output
+    /// re-encoded, so the average of the dithered pixels matches the source brightness.
+    pub linear_light: bool,
+    /// Number of evenly spaced gray levels in the output (2–256; smaller values mean 2).
+    /// Ignored when a palette is set.
+    pub levels: u32,
+    /// Weights used to convert RGB to gray.
+    pub luma: LumaModel,
+    /// Alternate the scan direction on every row in the error-diffusion algorithms.
+    pub serpentine: bool,
+    /// Side length of generated noise threshold maps (0 selects the default).
+    pub noise_size: u32,
+    /// Seed for the algorithms that use random numbers.
+    pub seed: u32,
+    /// Halftone cell size in pixels (2–256); smaller cells correspond to a higher screen
+    /// ruling (LPI). Line screens use it as the line spacing.
+    pub halftone_cell: f32,
+    /// Halftone screen angle in degrees; line screens run along it.
+    pub halftone_angle: f32,
+    /// Dot shape grown in each halftone cell.
+    pub dot_shape: DotShape,
+    /// Screen angles in degrees for the C, M, Y and K separations of the CMYK halftone.
+    pub screen_angles: [f32; 4],
+    /// Share of the common gray component printed in black (GCR), 0–1.
+    pub black_generation: f32,
+    /// Share of the generated black removed from C, M and Y (UCR), 0–1.
+    pub under_color_removal: f32,
+    /// Eschbach–Knox edge enhancement for the error-diffusion algorithms, as K − 1 for an
+    /// edge gain K: 0 disables it, larger values sharpen edges and negative values (down
+    /// to -1) soften them.
+    pub edge_enhancement: f32,
+    /// Previous frame of a sequence being dithered with [`FrameDitherer`]; only the
+    /// error-diffusion algorithms use it.
+    pub previous_frame: Option<PreviousFrame>,
+    /// Number of dots placed by the stippling algorithm (0 picks it from the image's ink).
+    pub stipple_count: u32,
+    /// Rounds of Lloyd relaxation the stippling algorithm runs.
+    pub stipple_iterations: u32,
 }
 
 /// The trait now requires Send + Sync so that it can be stored in a global registry.
@@ -167,130 +329,359 @@ pub trait DitherAlgorithm: Send + Sync {
 
 // === Generic Error Diffusion Helper ===
 
+/// Eschbach–Knox threshold modulation for every pixel of a `w`×`h` grid: `gain` times how
+/// far its original value sits above mid-gray. With `gain` = K − 1, quantizing the diffused
+/// value plus this term lowers the threshold by (K − 1)(I − mid), which sharpens edges by
+/// a factor of K. The error is still taken against the diffused value, so the error buffer
+/// absorbs the offset and flat areas keep their tone, save for the error still held in the
+/// buffer when the last rows are reached.
+/// Returns `None` when `gain` is 0 or the grid is empty.
+fn edge_modulation<const N: usize>(values: &[[f32; N]], w: usize, h: usize, gain: f32) -> Option<Vec<[f32; N]>> {
+    if gain == 0.0 || w == 0 || h == 0 {
+        return None;
+    }
+    Some(values.par_iter().map(|value| value.map(|v| gain * (v - 127.5))).collect())
+}
+
+/// The previous frame of a sequence resampled to a dither grid: its output in the working
+/// space of the pass, and how strongly each cell leans towards it (0–1).
+struct FrameReference<const N: usize> {
+    output: Vec<[f32; N]>,
+    weights: Vec<f32>,
+}
+
+/// Adds temporal hysteresis to `modulation`: each cell leans towards the previous frame's
+/// output by its weight times the distance from its original value to that output. Like
+/// the edge term this only moves the threshold, so tone is preserved while unchanged areas
+/// tend to repeat last frame's pattern instead of reshuffling it.
+fn with_temporal_modulation<const N: usize>(
+    modulation: Option<Vec<[f32; N]>>,
+    values: &[[f32; N]],
+    reference: Option<&FrameReference<N>>,
+) -> Option<Vec<[f32; N]>> {
+    let Some(reference) = reference else {
+        return modulation;
+    };
+    let mut modulation = modulation.unwrap_or_else(|| vec![[0.0; N]; values.len()]);
+    modulation.par_iter_mut().enumerate().for_each(|(i, out)| {
+        let weight = reference.weights[i];
+        for c in 0..N {
+            out[c] += weight * (reference.output[i][c] - values[i][c]);
+        }
+    });
+    Some(modulation)
+}
+
+/// Grayscale form of [`edge_modulation`] followed by [`with_temporal_modulation`].
+fn threshold_modulation_gray(
+    gray: &[f32],
+    w: usize,
+    h: usize,
+    gain: f32,
+    reference: Option<&FrameReference<1>>,
+) -> Option<Vec<f32>> {
+    if gain == 0.0 && reference.is_none() {
+        return None;
+    }
+    let values: Vec<[f32; 1]> = gray.iter().map(|&v| [v]).collect();
+    with_temporal_modulation(edge_modulation(&values, w, h, gain), &values, reference)
+        .map(|modulation| modulation.into_iter().map(|[v]| v).collect())
+}
+
+/// `value` as seen by the quantizer: shifted by the threshold modulation at `idx`, if any.
+#[inline(always)]
+fn edge_enhanced(value: f32, modulation: &Option<Vec<f32>>, idx: usize) -> f32 {
+    modulation.as_ref().map_or(value, |modulation| value + modulation[idx])
+}
+
+/// Per-channel form of [`edge_enhanced`] for palette matching.
+#[inline(always)]
+fn edge_enhanced_color(value: [f32; 3], modulation: &Option<Vec<[f32; 3]>>, idx: usize) -> [f32; 3] {
+    modulation.as_ref().map_or(value, |modulation| {
+        let offset = modulation[idx];
+        [value[0] + offset[0], value[1] + offset[1], value[2] + offset[2]]
+    })
+}
+
+/// Runs table-driven error diffusion over a grayscale buffer in place, with the threshold
+/// and levels of `params`. With `serpentine` set, odd rows are scanned right-to-left and
+/// the kernel is mirrored horizontally. `edge_enhancement` is the gain passed to
+/// [`edge_modulation`]; `reference` adds temporal hysteresis towards the previous frame.
+fn diffuse_gray(
+    gray: &mut [f32],
+    w: usize,
+    h: usize,
+    kernel: &[(isize, isize, f32)],
+    params: &DitherParams,
+    reference: Option<&FrameReference<1>>,
+) {
+    let threshold = working_threshold(params.threshold, params.linear_light);
+    let modulation = threshold_modulation_gray(gray, w, h, params.edge_enhancement, reference);
+    let w = w as isize;
+    let h = h as isize;
+    for y in 0..h {
+        let reverse = params.serpentine && y % 2 == 1;
+        let dir = if reverse { -1 } else { 1 };
+        for i in 0..w {
+            let x = if reverse { w - 1 - i } else { i };
+            let idx = (y * w + x) as usize;
+            let old_pixel = gray[idx];
+            let new_pixel = quantize_gray(edge_enhanced(old_pixel, &modulation, idx), threshold, params.levels);
+            let error = old_pixel - new_pixel;
+            gray[idx] = new_pixel;
+            for &(dx, dy, factor) in kernel {
+                let nx = x + dx * dir;
+                let ny = y + dy;
+                if nx >= 0 && nx < w && ny >= 0 && ny < h {
+                    let nidx = (ny * w + nx) as usize;
+                    gray[nidx] += error * factor;
+                }
+            }
+        }
+    }
+}
+
 fn error_diffusion_generic(
     data: &mut [u8],
     width: u32,
     height: u32,
-    threshold: u8,
     kernel: &[(isize, isize, f32)],
-    block_scale: u32,
+    params: &DitherParams,
 ) {
-    if block_scale > 1 {
-        let bs = block_scale as usize;
-        let w = width as usize;
-        let h = height as usize;
-        let new_w = (w + bs - 1) / bs;
-        let new_h = (h + bs - 1) / bs;
-        
-        // Downsample grayscale values.
-        let mut down_gray = vec![0.0; new_w * new_h];
-        let mut gray = vec![0.0; w * h];
-        for (i, chunk) in data.chunks_exact(4).enumerate() {
-            let r = chunk[0] as f32;
-            let g = chunk[1] as f32;
-            let b = chunk[2] as f32;
-            gray[i] = 0.299 * r + 0.587 * g + 0.114 * b;
-        }
-        for ny in 0..new_h {
-            for nx in 0..new_w {
-                let mut sum = 0.0;
-                let mut count = 0;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        sum += gray[y * w + x];
-                        count += 1;
-                    }
+    let bs = params.block_scale.max(1) as usize;
+    let w = width as usize;
+    let h = height as usize;
+    if let Some(palette) = params.palette.as_ref() {
+        let reference = params.previous_frame.as_ref().map(|frame| frame.palette_reference(w, h, bs, palette));
+        error_diffusion_palette(data, width, height, kernel, palette, params, reference.as_ref());
+        return;
+    }
+    let mut gray = grayscale(data, params.luma, params.linear_light);
+    if bs > 1 {
+        // Downsample grayscale values; diffusion then runs on the block grid.
+        gray = downsample_gray(&gray, w, h, bs);
+    }
+    let reference = params
+        .previous_frame
+        .as_ref()
+        .map(|frame| frame.gray_reference(w, h, bs, params.luma, params.linear_light));
+    diffuse_gray(&mut gray, w.div_ceil(bs), h.div_ceil(bs), kernel, params, reference.as_ref());
+    // Upscale back into the full resolution data.
+    write_gray(data, &gray, w, h, bs, params.linear_light);
+}
+
+// === Palette Error Diffusion Helper ===
+
+/// Averages each `bs`×`bs` block of RGB values into a downsampled RGB buffer.
+fn downsample_rgb(data: &[u8], w: usize, h: usize, bs: usize) -> Vec<[f32; 3]> {
+    if w == 0 || h == 0 {
+        return Vec::new();
+    }
+    let new_w = w.div_ceil(bs);
+    let new_h = h.div_ceil(bs);
+    let mut down = vec![[0.0f32; 3]; new_w * new_h];
+    down.par_chunks_mut(new_w).enumerate().for_each(|(ny, row)| {
+        for (nx, out) in row.iter_mut().enumerate() {
+            let mut sum = [0.0f32; 3];
+            let mut count = 0;
+            let y_end = ((ny + 1) * bs).min(h);
+            let x_end = ((nx + 1) * bs).min(w);
+            for y in ny * bs..y_end {
+                for x in nx * bs..x_end {
+                    let idx = (y * w + x) * 4;
+                    sum[0] += data[idx] as f32;
+                    sum[1] += data[idx + 1] as f32;
+                    sum[2] += data[idx + 2] as f32;
+                    count += 1;
                 }
-                down_gray[ny * new_w + nx] = sum / (count as f32);
             }
+            let n = count as f32;
+            *out = [sum[0] / n, sum[1] / n, sum[2] / n];
         }
-        
-        // Apply error diffusion on the downsampled grid.
-        for y in 0..new_h {
-            for x in 0..new_w {
-                let idx = y * new_w + x;
-                let old_pixel = down_gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                down_gray[idx] = new_pixel;
-                for &(dx, dy, factor) in kernel {
-                    let nx = x as isize + dx;
-                    let ny = y as isize + dy;
-                    if nx >= 0 && nx < new_w as isize && ny >= 0 && ny < new_h as isize {
-                        let nidx = (ny as usize) * new_w + (nx as usize);
-                        down_gray[nidx] += error * factor;
+    });
+    down
+}
+
+/// Writes each downsampled RGB value back over its `bs`×`bs` block.
+fn upscale_rgb(data: &mut [u8], w: usize, h: usize, bs: usize, down: &[[f32; 3]]) {
+    if w == 0 || h == 0 {
+        return;
+    }
+    let new_w = w.div_ceil(bs);
+    let data_ptr = data.as_mut_ptr() as usize;
+    (0..h.div_ceil(bs)).into_par_iter().for_each(|ny| {
+        for nx in 0..new_w {
+            let color = down[ny * new_w + nx];
+            let y_end = ((ny + 1) * bs).min(h);
+            let x_end = ((nx + 1) * bs).min(w);
+            for y in ny * bs..y_end {
+                for x in nx * bs..x_end {
+                    let idx = (y * w + x) * 4;
+                    unsafe {
+                        let ptr = (data_ptr + idx) as *mut u8;
+                        *ptr = color[0].round().clamp(0.0, 255.0) as u8;
+                        *ptr.add(1) = color[1].round().clamp(0.0, 255.0) as u8;
+                        *ptr.add(2) = color[2].round().clamp(0.0, 255.0) as u8;
                     }
                 }
             }
         }
-        
-        // Upscale back into the full resolution data.
-        let data_ptr = data.as_mut_ptr() as usize; // store as usize for sharing
-        (0..new_h).into_par_iter().for_each(|ny| {
-            for nx in 0..new_w {
-                let val = down_gray[ny * new_w + nx].round().clamp(0.0, 255.0) as u8;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        let idx = (y * w + x) * 4;
-                        unsafe {
-                            let ptr = (data_ptr + idx) as *mut u8;
-                            *ptr = val;
-                            *ptr.add(1) = val;
-                            *ptr.add(2) = val;
-                        }
-                    }
-                }
-            }
-        });
-    
-    } else {
-        // Full-resolution error diffusion (block_scale == 1)
-        let w = width as isize;
-        let h = height as isize;
-        let mut gray: Vec<f32> = data.chunks_exact(4)
-            .map(|chunk| 0.299 * (chunk[0] as f32)
-                       + 0.587 * (chunk[1] as f32)
-                       + 0.114 * (chunk[2] as f32))
-            .collect();
-        
-        for y in 0..h {
-            for x in 0..w {
-                let idx = (y * w + x) as usize;
-                let old_pixel = gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                gray[idx] = new_pixel;
-                for &(dx, dy, factor) in kernel {
-                    let nx = x + dx;
-                    let ny = y + dy;
-                    if nx >= 0 && nx < w && ny >= 0 && ny < h {
-                        let nidx = (ny * w + nx) as usize;
-                        gray[nidx] += error * factor;
-                    }
+    });
+}
+
+/// Error diffusion against an arbitrary palette: each pixel snaps to its nearest
+/// palette color and the per-channel error is spread with `kernel`. Both the match and the
+/// error are computed in the working space of the palette's color metric. The block scale,
+/// `serpentine`, `edge_enhancement` and `reference` behave as in [`diffuse_gray`].
+fn error_diffusion_palette(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    kernel: &[(isize, isize, f32)],
+    palette: &Palette,
+    params: &DitherParams,
+    reference: Option<&FrameReference<3>>,
+) {
+    let bs = params.block_scale.max(1) as usize;
+    let w = width as usize;
+    let h = height as usize;
+    let new_w = w.div_ceil(bs);
+    let new_h = h.div_ceil(bs);
+    let mut colors = downsample_rgb(data, w, h, bs);
+    colors.par_iter_mut().for_each(|c| *c = palette.to_space(*c));
+    let modulation =
+        with_temporal_modulation(edge_modulation(&colors, new_w, new_h, params.edge_enhancement), &colors, reference);
+    let mut indices = vec![0usize; colors.len()];
+
+    for y in 0..new_h as isize {
+        let reverse = params.serpentine && y % 2 == 1;
+        let dir = if reverse { -1 } else { 1 };
+        for i in 0..new_w as isize {
+            let x = if reverse { new_w as isize - 1 - i } else { i };
+            let idx = (y as usize) * new_w + (x as usize);
+            let old_color = colors[idx];
+            let index = palette.nearest_index(edge_enhanced_color(old_color, &modulation, idx));
+            let new_color = palette.space_color(index);
+            let error = [
+                old_color[0] - new_color[0],
+                old_color[1] - new_color[1],
+                old_color[2] - new_color[2],
+            ];
+            indices[idx] = index;
+            for &(dx, dy, factor) in kernel {
+                let nx = x + dx * dir;
+                let ny = y + dy;
+                if nx >= 0 && nx < new_w as isize && ny >= 0 && ny < new_h as isize {
+                    let target = &mut colors[(ny as usize) * new_w + (nx as usize)];
+                    target[0] += error[0] * factor;
+                    target[1] += error[1] * factor;
+                    target[2] += error[2] * factor;
                 }
             }
         }
-        let data_ptr = data.as_mut_ptr() as usize;
-        for (i, _chunk) in data.chunks_exact_mut(4).enumerate() {
-            let val = gray[i].round().clamp(0.0, 255.0) as u8;
-            unsafe {
-                let ptr = (data_ptr + i * 4) as *mut u8;
-                *ptr = val;
-                *ptr.add(1) = val;
-                *ptr.add(2) = val;
-            }
-        }
     }
+
+    let output: Vec<[f32; 3]> = indices.iter().map(|&i| palette.color(i)).collect();
+    upscale_rgb(data, w, h, bs, &output);
 }
 
+// === Kernels for the Classic Diffusion Algorithms ===
+
+// Floyd–Steinberg: Sum = 16
+const FLOYD_STEINBERG_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 7.0/16.0),
+    (-1, 1, 3.0/16.0),
+    (0, 1, 5.0/16.0),
+    (1, 1, 1.0/16.0),
+];
+
+// Jarvis–Judice–Ninke: Sum = 48
+const JARVIS_JUDICE_NINKE_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 7.0/48.0),
+    (2, 0, 5.0/48.0),
+    (-2, 1, 3.0/48.0),
+    (-1, 1, 5.0/48.0),
+    (0, 1, 7.0/48.0),
+    (1, 1, 5.0/48.0),
+    (2, 1, 3.0/48.0),
+    (-2, 2, 1.0/48.0),
+    (-1, 2, 3.0/48.0),
+    (0, 2, 5.0/48.0),
+    (1, 2, 3.0/48.0),
+    (2, 2, 1.0/48.0),
+];
+
+// Atkinson: 6 taps of 1/8 (only 3/4 of the error is propagated)
+const ATKINSON_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 1.0/8.0),
+    (2, 0, 1.0/8.0),
+    (-1, 1, 1.0/8.0),
+    (0, 1, 1.0/8.0),
+    (1, 1, 1.0/8.0),
+    (0, 2, 1.0/8.0),
+];
+
+// === Additional Classic Kernels ===
+
+// Stucki: Sum = 42
+const STUCKI_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 8.0/42.0),
+    (2, 0, 4.0/42.0),
+    (-2, 1, 2.0/42.0),
+    (-1, 1, 4.0/42.0),
+    (0, 1, 8.0/42.0),
+    (1, 1, 4.0/42.0),
+    (2, 1, 2.0/42.0),
+    (-2, 2, 1.0/42.0),
+    (-1, 2, 2.0/42.0),
+    (0, 2, 4.0/42.0),
+    (1, 2, 2.0/42.0),
+    (2, 2, 1.0/42.0),
+];
+
+// Burkes: Sum = 32
+const BURKES_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 8.0/32.0),
+    (2, 0, 4.0/32.0),
+    (-2, 1, 2.0/32.0),
+    (-1, 1, 4.0/32.0),
+    (0, 1, 8.0/32.0),
+    (1, 1, 4.0/32.0),
+    (2, 1, 2.0/32.0),
+];
+
+// Fan: Sum = 16
+const FAN_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 7.0/16.0),
+    (-2, 1, 1.0/16.0),
+    (-1, 1, 3.0/16.0),
+    (0, 1, 5.0/16.0),
+];
+
+// Shiau–Fan (4-cell): Sum = 8
+const SHIAU_FAN_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 4.0/8.0),
+    (-2, 1, 1.0/8.0),
+    (-1, 1, 1.0/8.0),
+    (0, 1, 2.0/8.0),
+];
+
+// Shiau–Fan (5-cell): Sum = 16
+const SHIAU_FAN_2_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 8.0/16.0),
+    (-3, 1, 1.0/16.0),
+    (-2, 1, 1.0/16.0),
+    (-1, 1, 2.0/16.0),
+    (0, 1, 4.0/16.0),
+];
+
+// False Floyd–Steinberg: Sum = 8
+const FALSE_FLOYD_STEINBERG_KERNEL: &[(isize, isize, f32)] = &[
+    (1, 0, 3.0/8.0),
+    (0, 1, 3.0/8.0),
+    (1, 1, 2.0/8.0),
+];
+
 // === Kernels for Sierra Variants ===
 
 // Sierra (Sierra‑3): Sum = 32
@@ -325,29 +716,95 @@ const SIERRA_LITE_KERNEL: &[(isize, isize, f32)] = &[
     (1, 1, 1.0/4.0),
 ];
 
-// === Sierra Dithering Implementations ===
+// === Kernel-Driven Dithering Implementations ===
+
+/// Preprocesses and runs `error_diffusion_generic` with `kernel`, taking every other
+/// setting from `params`.
+fn apply_kernel_dither(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    params: &DitherParams,
+    kernel: &[(isize, isize, f32)],
+) {
+    apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+    error_diffusion_generic(data, width, height, kernel, params);
+}
+
+pub struct JarvisJudiceNinkeDither;
+impl DitherAlgorithm for JarvisJudiceNinkeDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, JARVIS_JUDICE_NINKE_KERNEL);
+    }
+}
+
+pub struct AtkinsonDither;
+impl DitherAlgorithm for AtkinsonDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, ATKINSON_KERNEL);
+    }
+}
 
 pub struct SierraDither;
 impl DitherAlgorithm for SierraDither {
     fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
-         apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
-         error_diffusion_generic(data, width, height, params.threshold, SIERRA_KERNEL, params.block_scale);
+         apply_kernel_dither(data, width, height, params, SIERRA_KERNEL);
     }
 }
 
 pub struct SierraTwoRowDither;
 impl DitherAlgorithm for SierraTwoRowDither {
     fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
-         apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
-         error_diffusion_generic(data, width, height, params.threshold, SIERRA_TWO_ROW_KERNEL, params.block_scale);
+         apply_kernel_dither(data, width, height, params, SIERRA_TWO_ROW_KERNEL);
     }
 }
 
 pub struct SierraLiteDither;
 impl DitherAlgorithm for SierraLiteDither {
     fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
-         apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
-         error_diffusion_generic(data, width, height, params.threshold, SIERRA_LITE_KERNEL, params.block_scale);
+         apply_kernel_dither(data, width, height, params, SIERRA_LITE_KERNEL);
+    }
+}
+
+pub struct StuckiDither;
+impl DitherAlgorithm for StuckiDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, STUCKI_KERNEL);
+    }
+}
+
+pub struct BurkesDither;
+impl DitherAlgorithm for BurkesDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, BURKES_KERNEL);
+    }
+}
+
+pub struct FanDither;
+impl DitherAlgorithm for FanDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, FAN_KERNEL);
+    }
+}
+
+pub struct ShiauFanDither;
+impl DitherAlgorithm for ShiauFanDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, SHIAU_FAN_KERNEL);
+    }
+}
+
+pub struct ShiauFan2Dither;
+impl DitherAlgorithm for ShiauFan2Dither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, SHIAU_FAN_2_KERNEL);
+    }
+}
+
+pub struct FalseFloydSteinbergDither;
+impl DitherAlgorithm for FalseFloydSteinbergDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+         apply_kernel_dither(data, width, height, params, FALSE_FLOYD_STEINBERG_KERNEL);
     }
 }
 
@@ -360,15 +817,22 @@ impl DitherAlgorithm for ThresholdDither {
          apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
          let total_pixels = data.len() / 4;
          let data_ptr = data.as_mut_ptr() as usize;
+         let threshold = working_threshold(params.threshold, params.linear_light);
+         if let Some(palette) = &params.palette {
+             data.par_chunks_mut(4).for_each(|chunk| {
+                 let color = palette.nearest([chunk[0] as f32, chunk[1] as f32, chunk[2] as f32]);
+                 chunk[0] = color[0] as u8;
+                 chunk[1] = color[1] as u8;
+                 chunk[2] = color[2] as u8;
+             });
+             return;
+         }
          (0..total_pixels).into_par_iter().for_each(|i| {
              let idx = i * 4;
              unsafe {
                  let ptr = (data_ptr + idx) as *mut u8;
-                 let r = *ptr as f32;
-                 let g = *ptr.add(1) as f32;
-                 let b = *ptr.add(2) as f32;
-                 let luminance = 0.299 * r + 0.587 * g + 0.114 * b;
-                 let new_value = if luminance < params.threshold as f32 { 0 } else { 255 };
+                 let lum = luminance(*ptr, *ptr.add(1), *ptr.add(2), params.luma, params.linear_light);
+                 let new_value = encode_gray(quantize_gray(lum, threshold, params.levels), params.linear_light);
                  *ptr = new_value;
                  *ptr.add(1) = new_value;
                  *ptr.add(2) = new_value;
@@ -381,141 +845,66 @@ impl DitherAlgorithm for ThresholdDither {
 pub struct FloydSteinbergDither;
 impl DitherAlgorithm for FloydSteinbergDither {
     fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
-         floyd_steinberg_dither_impl(
-             data,
-             width,
-             height,
-             params.threshold,
-             params.contrast,
-             params.gamma,
-             params.pixelation,
-             params.blur,
-             params.block_scale,
-         );
+         apply_kernel_dither(data, width, height, params, FLOYD_STEINBERG_KERNEL);
     }
 }
 
-fn floyd_steinberg_dither_impl(
-    data: &mut [u8],
-    width: u32,
-    height: u32,
-    threshold: u8,
-    contrast: f32,
-    gamma: f32,
-    pixelation: u32,
-    blur: u32,
-    block_scale: u32,
-) {
-    apply_preprocessing(data, width, height, contrast, gamma, pixelation, blur);
-    let w = width as usize;
-    let h = height as usize;
-    let mut gray: Vec<f32> = data.chunks_exact(4)
-        .map(|chunk| 0.299 * (chunk[0] as f32)
-                   + 0.587 * (chunk[1] as f32)
-                   + 0.114 * (chunk[2] as f32))
-        .collect();
-    
-    if block_scale > 1 {
-        let bs = block_scale as usize;
-        let new_w = (w + bs - 1) / bs;
-        let new_h = (h + bs - 1) / bs;
-        let mut down_gray = vec![0.0; new_w * new_h];
-        let down_gray_ptr = down_gray.as_mut_ptr() as usize;
-        (0..new_h).into_par_iter().for_each(|ny| {
-            for nx in 0..new_w {
-                let mut sum = 0.0;
-                let mut count = 0;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        sum += gray[y * w + x];
-                        count += 1;
-                    }
-                }
-                unsafe {
-                    let ptr = (down_gray_ptr + (ny * new_w + nx) * size_of::<f32>()) as *mut f32;
-                    *ptr = sum / (count as f32);
-                }
-            }
-        });
-        for ny in 0..new_h {
-            for nx in 0..new_w {
-                let idx = ny * new_w + nx;
-                let old_pixel = down_gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                down_gray[idx] = new_pixel;
-                if nx + 1 < new_w {
-                    down_gray[idx + 1] += error * 7.0 / 16.0;
-                }
-                if nx > 0 && ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + (nx - 1)] += error * 3.0 / 16.0;
-                }
-                if ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + nx] += error * 5.0 / 16.0;
-                }
-                if nx + 1 < new_w && ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + (nx + 1)] += error * 1.0 / 16.0;
-                }
-            }
+// === Threshold Maps ===
+
+/// A threshold texture tiled across the image by the ordered-dithering path. Entries are
+/// normalized to (0, 1).
+struct ThresholdMap {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+
+impl ThresholdMap {
+    /// Builds a map from row-major ranks `0..width * height`; rank `r` becomes
+    /// `(r + 0.5) / (width * height)`.
+    fn from_ranks(width: usize, height: usize, ranks: &[u32]) -> ThresholdMap {
+        let total = (width * height) as f32;
+        ThresholdMap {
+            width,
+            height,
+            values: ranks.iter().map(|&r| (r as f32 + 0.5) / total).collect(),
         }
-        let data_ptr = data.as_mut_ptr() as usize;
-        (0..new_h).into_par_iter().for_each(|ny| {
-            for nx in 0..new_w {
-                let val = down_gray[ny * new_w + nx].round().clamp(0.0, 255.0) as u8;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        let idx = (y * w + x) * 4;
-                        unsafe {
-                            let ptr = (data_ptr + idx) as *mut u8;
-                            *ptr = val;
-                            *ptr.add(1) = val;
-                            *ptr.add(2) = val;
-                        }
-                    }
-                }
-            }
-        });
-    } else {
-        for y in 0..h {
-            for x in 0..w {
-                let idx = y * w + x;
-                let old_pixel = gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                gray[idx] = new_pixel;
-                if x + 1 < w {
-                    gray[idx + 1] += error * 7.0 / 16.0;
-                }
-                if x > 0 && y + 1 < h {
-                    gray[idx + w - 1] += error * 3.0 / 16.0;
-                }
-                if y + 1 < h {
-                    gray[idx + w] += error * 5.0 / 16.0;
-                }
-                if x + 1 < w && y + 1 < h {
-                    gray[idx + w + 1] += error * 1.0 / 16.0;
-                }
-            }
+    }
+
+    /// Builds a map from arbitrary row-major levels, scaled by the largest entry so both
+    /// rank matrices (`0..n`) and full-range textures (`0..=255`, `0..=65535`) work.
+    fn from_levels(width: usize, height: usize, levels: &[u32]) -> ThresholdMap {
+        let scale = levels.iter().copied().max().unwrap_or(0) as f32 + 1.0;
+        ThresholdMap {
+            width,
+            height,
+            values: levels.iter().map(|&v| (v as f32 + 0.5) / scale).collect(),
         }
-        let data_ptr = data.as_mut_ptr() as usize;
-        for (i, _chunk) in data.chunks_exact_mut(4).enumerate() {
-            let val = gray[i].round().clamp(0.0, 255.0) as u8;
-            unsafe {
-                let ptr = (data_ptr + i * 4) as *mut u8;
-                *ptr = val;
-                *ptr.add(1) = val;
-                *ptr.add(2) = val;
-            }
+    }
+
+    /// Evaluates `threshold` at every pixel of a `width`×`height` image.
+    fn from_fn(width: usize, height: usize, threshold: impl Fn(usize, usize) -> f32 + Sync) -> ThresholdMap {
+        ThresholdMap {
+            width,
+            height,
+            values: (0..height)
+                .into_par_iter()
+                .flat_map_iter(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| threshold(x, y))
+                .collect(),
         }
     }
+
+    fn from_matrix(matrix: &[Vec<u8>]) -> ThresholdMap {
+        let ranks: Vec<u32> = matrix.iter().flatten().map(|&v| v as u32).collect();
+        ThresholdMap::from_ranks(matrix[0].len(), matrix.len(), &ranks)
+    }
+
+    /// Threshold for pixel `(x, y)`, tiling the map.
+    #[inline(always)]
+    fn at(&self, x: usize, y: usize) -> f32 {
+        self.values[(y % self.height) * self.width + x % self.width]
+    }
 }
 
 // === Ordered Bayer Dithering Implementation ===
@@ -533,9 +922,9 @@ fn generate_bayer_matrix(width: u8, height: u8) -> Vec<Vec<u8>> {
     for value in 1..total {
         let mut best_dist = -1;
         let mut best_pos = (0, 0);
-        for y in 0..h {
-            for x in 0..w {
-                if used[y][x] {
+        for (y, used_row) in used.iter().enumerate() {
+            for (x, &is_used) in used_row.iter().enumerate() {
+                if is_used {
                     continue;
                 }
                 let mut min_dist = i32::MAX;
@@ -562,33 +951,45 @@ fn generate_bayer_matrix(width: u8, height: u8) -> Vec<Vec<u8>> {
     matrix
 }
 
-fn ordered_dither_bayer_impl(
-    data: &mut [u8],
-    width: u32,
-    height: u32,
-    contrast: f32,
-    gamma: f32,
-    pixelation: u32,
-    blur: u32,
-    block_scale: u32,
-    matrix_width: u8,
-    matrix_height: u8,
-) {
+/// Classic recursive Bayer index matrix of side `size`, rounded up to a power of two and
+/// clamped to 2–16. Built with M(2n) = [[4M, 4M+2], [4M+3, 4M+1]], unrolled per bit: the low
+/// coordinate bits select the most significant base-4 digit of each entry.
+fn generate_recursive_bayer_matrix(size: u8) -> Vec<Vec<u8>> {
+    let n = (size.clamp(2, 16) as usize).next_power_of_two();
+    let bits = n.trailing_zeros();
+    (0..n)
+        .map(|y| {
+            (0..n)
+                .map(|x| {
+                    let mut value = 0;
+                    for bit in 0..bits {
+                        let xb = (x >> bit) & 1;
+                        let yb = (y >> bit) & 1;
+                        value = (value << 2) | ((xb ^ yb) << 1) | yb;
+                    }
+                    value as u8
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn ordered_dither_impl(data: &mut [u8], width: u32, height: u32, params: &DitherParams, threshold_map: &ThresholdMap) {
     // Preprocess image
-    apply_preprocessing(data, width, height, contrast, gamma, pixelation, blur);
+    apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
     let w = width as usize;
     let h = height as usize;
-    let bs = block_scale as usize;
-    let mat_w = matrix_width.max(1) as usize;
-    let mat_h = matrix_height.max(1) as usize;
-    let bayer_matrix = generate_bayer_matrix(matrix_width.max(1), matrix_height.max(1));
-    let total_values = (mat_w * mat_h) as f32;
+    let bs = params.block_scale as usize;
+    if let Some(palette) = params.palette.as_ref() {
+        ordered_dither_palette(data, w, h, bs, threshold_map, palette);
+        return;
+    }
     if bs > 1 {
         let data_ptr = data.as_mut_ptr() as usize;
-        (0..(h + bs - 1) / bs).into_par_iter().for_each(|by| {
+        (0..h.div_ceil(bs)).into_par_iter().for_each(|by| {
             let y_start = by * bs;
             let y_end = ((by + 1) * bs).min(h);
-            for bx in 0..((w + bs - 1) / bs) {
+            for bx in 0..(w.div_ceil(bs)) {
                 let x_start = bx * bs;
                 let x_end = ((bx + 1) * bs).min(w);
                 let mut sum = 0.0;
@@ -598,20 +999,16 @@ fn ordered_dither_bayer_impl(
                         let idx = (y * w + x) * 4;
                         unsafe {
                             let ptr = (data_ptr + idx) as *mut u8;
-                            let r = *ptr as f32;
-                            let g = *ptr.add(1) as f32;
-                            let b = *ptr.add(2) as f32;
-                            sum += 0.299 * r + 0.587 * g + 0.114 * b;
+                            sum += luminance(*ptr, *ptr.add(1), *ptr.add(2), params.luma, params.linear_light);
                         }
                         count += 1;
                     }
                 }
                 let avg = sum / (count as f32);
-                let center_y = (y_start + ((y_end - y_start) / 2)) % mat_h;
-                let center_x = (x_start + ((x_end - x_start) / 2)) % mat_w;
-                let matrix_value = bayer_matrix[center_y][center_x] as f32;
-                let dither_threshold = ((matrix_value + 0.5) / total_values) * 255.0;
-                let new_val = if avg < dither_threshold { 0 } else { 255 };
+                let center_y = y_start + ((y_end - y_start) / 2);
+                let center_x = x_start + ((x_end - x_start) / 2);
+                let dither_threshold = threshold_map.at(center_x, center_y) * 255.0;
+                let new_val = encode_gray(quantize_gray(avg, dither_threshold, params.levels), params.linear_light);
                 for y in y_start..y_end {
                     for x in x_start..x_end {
                         let idx = (y * w + x) * 4;
@@ -632,13 +1029,9 @@ fn ordered_dither_bayer_impl(
                 let idx = (y * w + x) * 4;
                 unsafe {
                     let ptr = (data_ptr + idx) as *mut u8;
-                    let r = *ptr as f32;
-                    let g = *ptr.add(1) as f32;
-                    let b = *ptr.add(2) as f32;
-                    let lum = 0.299 * r + 0.587 * g + 0.114 * b;
-                    let matrix_value = bayer_matrix[y % mat_h][x % mat_w] as f32;
-                    let dither_threshold = ((matrix_value + 0.5) / total_values) * 255.0;
-                    let new_val = if lum < dither_threshold { 0 } else { 255 };
+                    let lum = luminance(*ptr, *ptr.add(1), *ptr.add(2), params.luma, params.linear_light);
+                    let dither_threshold = threshold_map.at(x, y) * 255.0;
+                    let new_val = encode_gray(quantize_gray(lum, dither_threshold, params.levels), params.linear_light);
                     *ptr = new_val;
                     *ptr.add(1) = new_val;
                     *ptr.add(2) = new_val;
@@ -648,417 +1041,2112 @@ fn ordered_dither_bayer_impl(
     }
 }
 
+/// Ordered dithering against a palette: each channel is offset by the matrix value
+/// (scaled to the palette's spread) before snapping to the nearest palette color.
+fn ordered_dither_palette(
+    data: &mut [u8],
+    w: usize,
+    h: usize,
+    bs: usize,
+    threshold_map: &ThresholdMap,
+    palette: &Palette,
+) {
+    if w == 0 || h == 0 {
+        return;
+    }
+    let bs = bs.max(1);
+    let spread = palette.spread();
+    let mut colors = downsample_rgb(data, w, h, bs);
+    let new_w = w.div_ceil(bs);
+    colors.par_chunks_mut(new_w).enumerate().for_each(|(ny, row)| {
+        for (nx, color) in row.iter_mut().enumerate() {
+            // Sample the matrix at the block center, matching the grayscale path.
+            let center_y = ny * bs + (((ny + 1) * bs).min(h) - ny * bs) / 2;
+            let center_x = nx * bs + (((nx + 1) * bs).min(w) - nx * bs) / 2;
+            let offset = (threshold_map.at(center_x, center_y) - 0.5) * spread;
+            let target = if palette.linear_light() {
+                // Offset in linear light so the dither pattern averages to the source intensity.
+                color.map(|c| linear_to_srgb(srgb_to_linear(c) + offset / 255.0))
+            } else {
+                color.map(|c| c + offset)
+            };
+            *color = palette.nearest(target);
+        }
+    });
+    upscale_rgb(data, w, h, bs, &colors);
+}
+
 // DitherAlgorithm Implementation for dynamic Bayer
 pub struct BayerDither;
 impl DitherAlgorithm for BayerDither {
     fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
-        ordered_dither_bayer_impl(
+        ordered_dither_impl(
             data,
             width,
             height,
-            params.contrast,
-            params.gamma,
-            params.pixelation,
-            params.blur,
-            params.block_scale,
-            params.bayer_width,
-            params.bayer_height,
+            params,
+            &ThresholdMap::from_matrix(&generate_bayer_matrix(params.bayer_width.max(1), params.bayer_height.max(1))),
         );
     }
 }
 
-
-// Jarvis–Judice–Ninke Dithering
-pub struct JarvisJudiceNinkeDither;
-impl DitherAlgorithm for JarvisJudiceNinkeDither {
+/// Ordered dithering with the textbook recursive Bayer matrix. The side length is the larger
+/// of `bayer_width` and `bayer_height`, rounded up to a power of two (2–16).
+pub struct RecursiveBayerDither;
+impl DitherAlgorithm for RecursiveBayerDither {
     fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
-         jarvis_judice_ninke_dither_impl(
-             data,
-             width,
-             height,
-             params.threshold,
-             params.contrast,
-             params.gamma,
-             params.pixelation,
-             params.blur,
-             params.block_scale,
-         );
+        ordered_dither_impl(
+            data,
+            width,
+            height,
+            params,
+            &ThresholdMap::from_matrix(&generate_recursive_bayer_matrix(params.bayer_width.max(params.bayer_height))),
+        );
     }
 }
 
-fn jarvis_judice_ninke_dither_impl(
-    data: &mut [u8],
-    width: u32,
-    height: u32,
-    threshold: u8,
-    contrast: f32,
-    gamma: f32,
-    pixelation: u32,
-    blur: u32,
-    block_scale: u32,
-) {
-    apply_preprocessing(data, width, height, contrast, gamma, pixelation, blur);
-    let w = width as usize;
-    let h = height as usize;
-    let mut gray: Vec<f32> = data.chunks_exact(4)
-        .map(|chunk| 0.299 * (chunk[0] as f32)
-                   + 0.587 * (chunk[1] as f32)
-                   + 0.114 * (chunk[2] as f32))
+// === Pattern Dithering ===
+
+/// Most colors a Knoll mixing plan holds; larger threshold maps are sampled at this
+/// resolution.
+const MAX_PATTERN_CANDIDATES: usize = 64;
+
+/// How a pattern-dithering pass decides which palette colors each pixel blends.
+#[derive(Clone, Copy)]
+enum MixingPlan {
+    Knoll,
+    Yliluoma,
+}
+
+/// `levels` evenly spaced grays as a palette, so the palette-only algorithms can produce
+/// grayscale output. The grays are spaced in the working domain, like [`quantize_gray`].
+fn gray_palette(levels: u32, linear_light: bool) -> Palette {
+    let steps = levels.clamp(2, 256) - 1;
+    let grays: Vec<u32> = (0..=steps)
+        .map(|i| encode_gray(i as f32 * 255.0 / steps as f32, linear_light) as u32 * 0x010101)
         .collect();
-    if block_scale > 1 {
-        let bs = block_scale as usize;
-        let new_w = (w + bs - 1) / bs;
-        let new_h = (h + bs - 1) / bs;
-        let mut down_gray = vec![0.0; new_w * new_h];
-        let down_gray_ptr = down_gray.as_mut_ptr() as usize;
-        (0..new_h).into_par_iter().for_each(|ny| {
-            for nx in 0..new_w {
-                let mut sum = 0.0;
-                let mut count = 0;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        sum += gray[y * w + x];
-                        count += 1;
-                    }
-                }
-                unsafe {
-                    let ptr = (down_gray_ptr + (ny * new_w + nx) * size_of::<f32>()) as *mut f32;
-                    *ptr = sum / (count as f32);
-                }
-            }
-        });
-        for ny in 0..new_h {
-            for nx in 0..new_w {
-                let idx = ny * new_w + nx;
-                let old_pixel = down_gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                down_gray[idx] = new_pixel;
-                if nx + 1 < new_w {
-                    down_gray[idx + 1] += error * 7.0 / 48.0;
-                }
-                if nx > 0 && ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + (nx - 1)] += error * 5.0 / 48.0;
-                }
-                if ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + nx] += error * 7.0 / 48.0;
-                }
-                if nx + 1 < new_w && ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + (nx + 1)] += error * 5.0 / 48.0;
-                }
-            }
+    Palette::from_hex(&grays).with_linear_light(linear_light)
+}
+
+/// Palette index shown by a pixel whose color is `target` (in the palette's working space)
+/// and whose threshold is `threshold`. Knoll plans are ordered by `luma`. `plan` is scratch
+/// space; its length sets how finely colors are mixed.
+#[inline(always)]
+fn pattern_color(
+    palette: &Palette,
+    target: [f32; 3],
+    threshold: f32,
+    mixing: MixingPlan,
+    luma: LumaModel,
+    plan: &mut [usize],
+) -> usize {
+    match mixing {
+        MixingPlan::Knoll => {
+            pattern::knoll_plan(palette, target, luma, plan);
+            plan[((threshold * plan.len() as f32) as usize).min(plan.len() - 1)]
         }
-        let data_ptr = data.as_mut_ptr() as usize;
-        (0..new_h).into_par_iter().for_each(|ny| {
-            for nx in 0..new_w {
-                let val = down_gray[ny * new_w + nx].round().clamp(0.0, 255.0) as u8;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        let idx = (y * w + x) * 4;
-                        unsafe {
-                            let ptr = (data_ptr + idx) as *mut u8;
-                            *ptr = val;
-                            *ptr.add(1) = val;
-                            *ptr.add(2) = val;
-                        }
-                    }
-                }
-            }
-        });
-    } else {
-        for y in 0..h {
-            for x in 0..w {
-                let idx = y * w + x;
-                let old_pixel = gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                gray[idx] = new_pixel;
-                if x + 1 < w {
-                    gray[idx + 1] += error * 7.0 / 48.0;
-                }
-                if x + 2 < w {
-                    gray[y * w + (x + 2)] += error * 5.0 / 48.0;
-                }
-                if y + 1 < h {
-                    if x >= 2 {
-                        gray[(y + 1) * w + (x - 2)] += error * 3.0 / 48.0;
-                    }
-                    if x >= 1 {
-                        gray[(y + 1) * w + (x - 1)] += error * 5.0 / 48.0;
-                    }
-                    gray[(y + 1) * w + x] += error * 7.0 / 48.0;
-                    if x + 1 < w {
-                        gray[(y + 1) * w + (x + 1)] += error * 5.0 / 48.0;
-                    }
-                    if x + 2 < w {
-                        gray[(y + 1) * w + (x + 2)] += error * 3.0 / 48.0;
-                    }
-                }
-                if y + 2 < h {
-                    if x >= 2 {
-                        gray[(y + 2) * w + (x - 2)] += error * 1.0 / 48.0;
-                    }
-                    if x >= 1 {
-                        gray[(y + 2) * w + (x - 1)] += error * 3.0 / 48.0;
-                    }
-                    gray[(y + 2) * w + x] += error * 5.0 / 48.0;
-                    if x + 1 < w {
-                        gray[(y + 2) * w + (x + 1)] += error * 3.0 / 48.0;
-                    }
-                    if x + 2 < w {
-                        gray[(y + 2) * w + (x + 2)] += error * 1.0 / 48.0;
-                    }
-                }
-            }
+        MixingPlan::Yliluoma => {
+            let (first, second, ratio) = pattern::yliluoma_mix(palette, target, plan.len());
+            if threshold < ratio { second } else { first }
         }
-        let data_ptr = data.as_mut_ptr() as usize;
-        data.chunks_exact_mut(4).enumerate().for_each(|(i, _)| {
-            let val = gray[i].round().clamp(0.0, 255.0) as u8;
-            unsafe {
-                let ptr = (data_ptr + i * 4) as *mut u8;
-                *ptr = val;
-                *ptr.add(1) = val;
-                *ptr.add(2) = val;
+    }
+}
+
+fn pattern_dither_impl(data: &mut [u8], width: u32, height: u32, params: &DitherParams, mixing: MixingPlan) {
+    apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+    let w = width as usize;
+    let h = height as usize;
+    if w == 0 || h == 0 {
+        return;
+    }
+    let bs = params.block_scale.max(1) as usize;
+    let new_w = w.div_ceil(bs);
+    let threshold_map =
+        ThresholdMap::from_matrix(&generate_bayer_matrix(params.bayer_width.max(1), params.bayer_height.max(1)));
+    let plan_len = (threshold_map.width * threshold_map.height).clamp(2, MAX_PATTERN_CANDIDATES);
+    // Sample the matrix at the block center, matching the ordered-dither paths.
+    let threshold_at = |nx: usize, ny: usize| {
+        let center_y = ny * bs + (((ny + 1) * bs).min(h) - ny * bs) / 2;
+        let center_x = nx * bs + (((nx + 1) * bs).min(w) - nx * bs) / 2;
+        threshold_map.at(center_x, center_y)
+    };
+
+    if let Some(palette) = params.palette.as_ref() {
+        let mut colors = downsample_rgb(data, w, h, bs);
+        colors.par_chunks_mut(new_w).enumerate().for_each(|(ny, row)| {
+            let mut plan = vec![0; plan_len];
+            for (nx, color) in row.iter_mut().enumerate() {
+                let target = palette.to_space(*color);
+                let index = pattern_color(palette, target, threshold_at(nx, ny), mixing, params.luma, &mut plan);
+                *color = palette.color(index);
             }
         });
+        upscale_rgb(data, w, h, bs, &colors);
+        return;
+    }
+
+    let palette = gray_palette(params.levels, params.linear_light);
+    let mut gray = grayscale(data, params.luma, params.linear_light);
+    if bs > 1 {
+        gray = downsample_gray(&gray, w, h, bs);
     }
+    gray.par_chunks_mut(new_w).enumerate().for_each(|(ny, row)| {
+        let mut plan = vec![0; plan_len];
+        for (nx, value) in row.iter_mut().enumerate() {
+            let index = pattern_color(&palette, [*value; 3], threshold_at(nx, ny), mixing, params.luma, &mut plan);
+            *value = palette.space_color(index)[0];
+        }
+    });
+    write_gray(data, &gray, w, h, bs, params.linear_light);
 }
 
-// Atkinson Dithering
-pub struct AtkinsonDither;
-impl DitherAlgorithm for AtkinsonDither {
+/// Thomas Knoll's pattern dithering: ordered dithering that mixes any number of palette
+/// colors per pixel, ordered by brightness along the Bayer matrix.
+pub struct KnollDither;
+impl DitherAlgorithm for KnollDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        pattern_dither_impl(data, width, height, params, MixingPlan::Knoll);
+    }
+}
+
+/// Yliluoma's ordered dithering algorithm 1: each pixel alternates between the two palette
+/// colors whose blend best matches it, in the proportion given by the Bayer matrix.
+pub struct YliluomaDither;
+impl DitherAlgorithm for YliluomaDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        pattern_dither_impl(data, width, height, params, MixingPlan::Yliluoma);
+    }
+}
+
+// === Blue-Noise Ordered Dithering ===
+
+const DEFAULT_BLUE_NOISE_SIZE: u32 = 64;
+
+/// Void-and-cluster textures keyed by size, which is clamped to 16–128, so the cache holds
+/// at most 113 maps. Generation is quadratic in the texture area, so each map is built once
+/// and reused across frames and seeds.
+static BLUE_NOISE_MAPS: Lazy<Mutex<HashMap<usize, Arc<ThresholdMap>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the blue-noise map for `size` (clamped to 16–128, 0 selects 64) and `seed`.
+/// Every seed views the same texture through its own wrap-around shift and one of its
+/// eight rotations and mirror images, each of which is still seamless blue noise.
+fn blue_noise_map(size: u32, seed: u32) -> ThresholdMap {
+    let size = if size == 0 { DEFAULT_BLUE_NOISE_SIZE } else { size.clamp(16, 128) } as usize;
+    let texture = BLUE_NOISE_MAPS
+        .lock()
+        .unwrap()
+        .entry(size)
+        .or_insert_with(|| Arc::new(ThresholdMap::from_ranks(size, size, &blue_noise::void_and_cluster(size, 0))))
+        .clone();
+    let mut rng = XorShift32::new(seed);
+    let (dx, dy) = (rng.next_u32() as usize % size, rng.next_u32() as usize % size);
+    let symmetry = rng.next_u32();
+    ThresholdMap::from_fn(size, size, |x, y| {
+        let (mut x, mut y) = ((x + dx) % size, (y + dy) % size);
+        if symmetry & 1 != 0 {
+            x = size - 1 - x;
+        }
+        if symmetry & 2 != 0 {
+            y = size - 1 - y;
+        }
+        if symmetry & 4 != 0 {
+            (x, y) = (y, x);
+        }
+        texture.at(x, y)
+    })
+}
+
+/// Ordered dithering against a void-and-cluster blue-noise texture: the even, pattern-free
+/// look of error diffusion with every pixel still processed independently.
+pub struct BlueNoiseDither;
+impl DitherAlgorithm for BlueNoiseDither {
     fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
-         atkinson_dither_impl(
-             data,
-             width,
-             height,
-             params.threshold,
-             params.contrast,
-             params.gamma,
-             params.pixelation,
-             params.blur,
-             params.block_scale,
-         );
+        ordered_dither_impl(data, width, height, params, &blue_noise_map(params.noise_size, params.seed));
     }
 }
 
-fn atkinson_dither_impl(
+// === Noise Threshold Dithering ===
+
+/// Ordered dithering against a threshold map covering the whole image, with `threshold`
+/// evaluated at every pixel.
+fn noise_dither_impl(
     data: &mut [u8],
     width: u32,
     height: u32,
-    threshold: u8,
-    contrast: f32,
-    gamma: f32,
-    pixelation: u32,
-    blur: u32,
-    block_scale: u32,
+    params: &DitherParams,
+    threshold: impl Fn(usize, usize) -> f32 + Sync,
 ) {
-    apply_preprocessing(data, width, height, contrast, gamma, pixelation, blur);
-    let w = width as usize;
-    let h = height as usize;
-    let mut gray: Vec<f32> = data.chunks_exact(4)
-        .map(|chunk| 0.299 * (chunk[0] as f32)
-                   + 0.587 * (chunk[1] as f32)
-                   + 0.114 * (chunk[2] as f32))
-        .collect();
-    if block_scale > 1 {
-        let bs = block_scale as usize;
-        let new_w = (w + bs - 1) / bs;
-        let new_h = (h + bs - 1) / bs;
-        let mut down_gray = vec![0.0; new_w * new_h];
-        let down_gray_ptr = down_gray.as_mut_ptr() as usize;
-        (0..new_h).into_par_iter().for_each(|ny| {
-            for nx in 0..new_w {
-                let mut sum = 0.0;
-                let mut count = 0;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        sum += gray[y * w + x];
-                        count += 1;
-                    }
-                }
-                unsafe {
-                    let ptr = (down_gray_ptr + (ny * new_w + nx) * size_of::<f32>()) as *mut f32;
-                    *ptr = sum / (count as f32);
-                }
-            }
+    ordered_dither_impl(
+        data,
+        width,
+        height,
+        params,
+        &ThresholdMap::from_fn(width as usize, height as usize, threshold),
+    );
+}
+
+/// Jorge Jimenez's interleaved gradient noise, shifted per `seed` the way its temporal
+/// variant shifts each frame (so there are 64 distinct patterns).
+fn interleaved_gradient_noise(x: usize, y: usize, seed: u32) -> f32 {
+    let offset = 5.588238 * (seed % 64) as f64;
+    let (x, y) = (x as f64 + offset, y as f64 + offset);
+    (52.982_918_9 * (0.067_110_56 * x + 0.005_837_15 * y).fract()).fract() as f32
+}
+
+/// Random dithering: every pixel gets an independent, uniformly distributed threshold.
+pub struct WhiteNoiseDither;
+impl DitherAlgorithm for WhiteNoiseDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        noise_dither_impl(data, width, height, params, |x, y| {
+            XorShift32::for_pixel(params.seed, x as u32, y as u32).next_f32()
         });
-        for ny in 0..new_h {
-            for nx in 0..new_w {
-                let idx = ny * new_w + nx;
-                let old_pixel = down_gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                down_gray[idx] = new_pixel;
-                if nx + 1 < new_w {
-                    down_gray[idx + 1] += error * 7.0 / 48.0;
-                }
-                if nx > 0 && ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + (nx - 1)] += error * 5.0 / 48.0;
-                }
-                if ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + nx] += error * 7.0 / 48.0;
-                }
-                if nx + 1 < new_w && ny + 1 < new_h {
-                    down_gray[(ny + 1) * new_w + (nx + 1)] += error * 5.0 / 48.0;
-                }
-            }
+    }
+}
+
+/// Adds TPDF (triangular probability density) noise spanning ±1 step to a working-domain
+/// gray value and rounds it to the nearest of `levels` gray levels. `noise` holds two
+/// uniform values in [0, 1) whose sum makes the triangle. Within a step of black or white
+/// the second value is scaled down, turning the triangle into a trapezoid that cannot push
+/// the value past either end: clipping is what would bias the tone, while the remaining
+/// uniform part alone keeps the average output equal to the input.
+#[inline(always)]
+fn tpdf_quantize(value: f32, noise: [f32; 2], levels: u32) -> f32 {
+    let steps = (levels.clamp(2, 256) - 1) as f32;
+    let step = 255.0 / steps;
+    let value = value.clamp(0.0, 255.0) / step;
+    let width = (2.0 * value).min(2.0 * (steps - value)).min(1.0);
+    let noisy = value + (noise[0] - 0.5) + (noise[1] - 0.5) * width;
+    noisy.round().clamp(0.0, steps) * step
+}
+
+/// TPDF dithering, as used for audio: zero-mean triangular noise spanning ±1 quantization
+/// step is added to every pixel before rounding it to the nearest level (see
+/// [`tpdf_quantize`]). Palette output offsets each color by the same noise, scaled by the palette spread.
+pub struct TriangularNoiseDither;
+impl DitherAlgorithm for TriangularNoiseDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        let noise = |x: usize, y: usize| {
+            let mut rng = XorShift32::for_pixel(params.seed, x as u32, y as u32);
+            [rng.next_f32(), rng.next_f32()]
+        };
+        if params.palette.is_some() {
+            noise_dither_impl(data, width, height, params, |x, y| {
+                let [a, b] = noise(x, y);
+                a + b - 0.5
+            });
+            return;
         }
-        let data_ptr = data.as_mut_ptr() as usize;
-        (0..new_h).into_par_iter().for_each(|ny| {
-            for nx in 0..new_w {
-                let val = down_gray[ny * new_w + nx].round().clamp(0.0, 255.0) as u8;
-                let y_start = ny * bs;
-                let y_end = ((ny + 1) * bs).min(h);
-                let x_start = nx * bs;
-                let x_end = ((nx + 1) * bs).min(w);
-                for y in y_start..y_end {
-                    for x in x_start..x_end {
-                        let idx = (y * w + x) * 4;
-                        unsafe {
-                            let ptr = (data_ptr + idx) as *mut u8;
-                            *ptr = val;
-                            *ptr.add(1) = val;
-                            *ptr.add(2) = val;
-                        }
-                    }
-                }
-            }
-        });
-    } else {
-        for y in 0..h {
-            for x in 0..w {
-                let idx = y * w + x;
-                let old_pixel = gray[idx];
-                let new_pixel = if old_pixel < threshold as f32 { 0.0 } else { 255.0 };
-                let error = old_pixel - new_pixel;
-                gray[idx] = new_pixel;
-                let distributed_error = error / 8.0;
-                if x + 1 < w {
-                    gray[y * w + (x + 1)] += distributed_error;
-                }
-                if x + 2 < w {
-                    gray[y * w + (x + 2)] += distributed_error;
-                }
-                if y + 1 < h {
-                    if x > 0 {
-                        gray[(y + 1) * w + (x - 1)] += distributed_error;
-                    }
-                    gray[(y + 1) * w + x] += distributed_error;
-                    if x + 1 < w {
-                        gray[(y + 1) * w + (x + 1)] += distributed_error;
-                    }
-                }
-                if y + 2 < h {
-                    gray[(y + 2) * w + x] += distributed_error;
-                }
-            }
+        apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+        let w = width as usize;
+        let h = height as usize;
+        let bs = params.block_scale.max(1) as usize;
+        let mut gray = grayscale(data, params.luma, params.linear_light);
+        if bs > 1 {
+            gray = downsample_gray(&gray, w, h, bs);
         }
-        let data_ptr = data.as_mut_ptr() as usize;
-        data.chunks_exact_mut(4).enumerate().for_each(|(i, _)| {
-            let val = gray[i].round().clamp(0.0, 255.0) as u8;
-            unsafe {
-                let ptr = (data_ptr + i * 4) as *mut u8;
-                *ptr = val;
-                *ptr.add(1) = val;
-                *ptr.add(2) = val;
+        let new_w = w.div_ceil(bs);
+        if new_w == 0 {
+            return;
+        }
+        gray.par_chunks_mut(new_w).enumerate().for_each(|(ny, row)| {
+            for (nx, value) in row.iter_mut().enumerate() {
+                *value = tpdf_quantize(*value, noise(nx, ny), params.levels);
             }
         });
+        write_gray(data, &gray, w, h, bs, params.linear_light);
     }
 }
 
-// Error diffusion kernels (e.g., SIERRA_KERNEL, SIERRA_TWO_ROW_KERNEL, SIERRA_LITE_KERNEL) are defined elsewhere in the code.
+/// Interleaved gradient noise: a cheap closed-form noise from real-time rendering whose
+/// diagonal structure spreads thresholds more evenly than white noise.
+pub struct InterleavedGradientNoiseDither;
+impl DitherAlgorithm for InterleavedGradientNoiseDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        noise_dither_impl(data, width, height, params, |x, y| interleaved_gradient_noise(x, y, params.seed));
+    }
+}
 
-static ALGORITHM_REGISTRY: Lazy<HashMap<&'static str, Box<dyn DitherAlgorithm>>> = Lazy::new(|| {
-    let mut m: HashMap<&'static str, Box<dyn DitherAlgorithm>> = HashMap::new();
-    m.insert("threshold", Box::new(ThresholdDither));
-    m.insert("floyd-steinberg", Box::new(FloydSteinbergDither));
-    m.insert("bayer", Box::new(BayerDither));
-    m.insert("jarvis", Box::new(JarvisJudiceNinkeDither));
-    m.insert("atkinson", Box::new(AtkinsonDither));
-    m.insert("sierra", Box::new(SierraDither));
-    m.insert("sierra-two-row", Box::new(SierraTwoRowDither));
-    m.insert("sierra-lite", Box::new(SierraLiteDither));
-    m
-});
+// === Clustered-Dot Halftoning ===
 
-#[wasm_bindgen]
-pub fn apply_dither(
-    algorithm: &str,
-    data: &mut [u8],
-    width: u32,
-    height: u32,
-    threshold: u8,
-    contrast: f32,
-    gamma: f32,
-    pixelation: u32,
-    blur: u32,
-    block_scale: u32,
-    bayer_width: u8,
-    bayer_height: u8,
-) {
-    // Step 1: Backup original RGBA data.
-    let backup = data.to_vec();
+const DEFAULT_HALFTONE_CELL: f32 = 8.0;
+const DEFAULT_HALFTONE_ANGLE: f32 = 45.0;
 
-    // Step 2: Premultiply RGB channels by alpha.
-    for chunk in data.chunks_exact_mut(4) {
-        let a = chunk[3] as f32 / 255.0;
-        chunk[0] = (chunk[0] as f32 * a).round() as u8;
-        chunk[1] = (chunk[1] as f32 * a).round() as u8;
-        chunk[2] = (chunk[2] as f32 * a).round() as u8;
-        // Alpha remains unchanged.
+/// AM halftoning: dots of `dot_shape` grow from the centers of a screen of
+/// `halftone_cell`-pixel cells rotated by `halftone_angle`.
+pub struct HalftoneDither;
+impl DitherAlgorithm for HalftoneDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        let screen = halftone::Screen::new(params.dot_shape, params.halftone_cell, params.halftone_angle);
+        ordered_dither_impl(
+            data,
+            width,
+            height,
+            params,
+            &ThresholdMap::from_fn(width as usize, height as usize, |x, y| screen.threshold(x, y)),
+        );
     }
+}
 
-    // Step 3: Apply selected dithering algorithm.
-    let params = DitherParams {
-        threshold,
-        contrast,
-        gamma,
-        pixelation,
-        blur,
-        block_scale,
-        bayer_width,
-        bayer_height,
-    };
-    if let Some(alg) = ALGORITHM_REGISTRY.get(algorithm) {
-         alg.apply(data, width, height, &params);
-    } else {
-         // Fallback to threshold dithering if algorithm not found.
-         ThresholdDither.apply(data, width, height, &params);
-    }
+// === CMYK Halftoning ===
 
-    // Step 4: Un-premultiply RGB channels.
-    for chunk in data.chunks_exact_mut(4) {
-        let alpha = chunk[3];
-        if alpha > 0 {
-            let inv = 255.0 / (alpha as f32);
-            chunk[0] = ((chunk[0] as f32 * inv).round()).min(255.0) as u8;
-            chunk[1] = ((chunk[1] as f32 * inv).round()).min(255.0) as u8;
-            chunk[2] = ((chunk[2] as f32 * inv).round()).min(255.0) as u8;
-        }
-    }
+const DEFAULT_BLACK_GENERATION: f32 = 1.0;
+const DEFAULT_UNDER_COLOR_REMOVAL: f32 = 1.0;
 
-    // Step 5: Restore original RGBA for non-opaque pixels.
-    for (i, chunk) in data.chunks_exact_mut(4).enumerate() {
-        let orig_alpha = backup[i * 4 + 3];
-        if orig_alpha != 255 {
-            chunk[0] = backup[i * 4];
-            chunk[1] = backup[i * 4 + 1];
-            chunk[2] = backup[i * 4 + 2];
-            chunk[3] = orig_alpha;
+/// Separates a preprocessed RGBA buffer into C, M, Y and K ink coverage and halftones each
+/// separation with its own rotated screen, as set up in `params`. Returns four planes of
+/// one byte per block, in C, M, Y, K order, with 1 wherever ink is printed.
+fn cmyk_halftone_plates(data: &[u8], w: usize, h: usize, params: &DitherParams) -> Vec<u8> {
+    if w == 0 || h == 0 {
+        return Vec::new();
+    }
+    let bs = params.block_scale.max(1) as usize;
+    let new_w = w.div_ceil(bs);
+    let new_h = h.div_ceil(bs);
+    let colors = downsample_rgb(data, w, h, bs);
+    let screens = params
+        .screen_angles
+        .map(|angle| halftone::Screen::new(params.dot_shape, params.halftone_cell, angle));
+    let mut plates = vec![0u8; 4 * new_w * new_h];
+    plates.par_chunks_mut(new_w).enumerate().for_each(|(row_index, row)| {
+        let (ink, ny) = (row_index / new_h, row_index % new_h);
+        // Sample the screen at the block center, matching the ordered path.
+        let center_y = ny * bs + (((ny + 1) * bs).min(h) - ny * bs) / 2;
+        for (nx, out) in row.iter_mut().enumerate() {
+            let center_x = nx * bs + (((nx + 1) * bs).min(w) - nx * bs) / 2;
+            let reflectance =
+                colors[ny * new_w + nx].map(|c| if params.linear_light { srgb_to_linear(c) } else { c / 255.0 });
+            let coverage = halftone::rgb_to_cmyk(reflectance, params.black_generation, params.under_color_removal)[ink];
+            *out = (coverage > 1.0 - screens[ink].threshold(center_x, center_y)) as u8;
         }
+    });
+    plates
+}
+
+/// Color halftone: CMYK separations screened at `screen_angles` and overprinted on white
+/// paper with ideal inks for preview. Palette and gray levels do not apply.
+pub struct CmykHalftoneDither;
+impl DitherAlgorithm for CmykHalftoneDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+        let w = width as usize;
+        let h = height as usize;
+        let bs = params.block_scale.max(1) as usize;
+        let plates = cmyk_halftone_plates(data, w, h, params);
+        let plane = plates.len() / 4;
+        let colors: Vec<[f32; 3]> = (0..plane)
+            .map(|i| {
+                // Cyan absorbs red, magenta green and yellow blue; black absorbs all three.
+                let paper = if plates[3 * plane + i] == 1 { 0.0 } else { 255.0 };
+                [0, 1, 2].map(|ink| if plates[ink * plane + i] == 1 { 0.0 } else { paper })
+            })
+            .collect();
+        upscale_rgb(data, w, h, bs, &colors);
     }
 }
 
+// === Line-Screen Halftoning ===
+
+/// Ordered dithering against a `pattern` line screen with lines `halftone_cell` pixels
+/// apart, rotated by `halftone_angle` about the image center.
+fn line_screen_dither_impl(data: &mut [u8], width: u32, height: u32, params: &DitherParams, pattern: LinePattern) {
+    let center = ((width / 2) as f32, (height / 2) as f32);
+    let screen = halftone::LineScreen::new(pattern, params.halftone_cell, params.halftone_angle, center);
+    ordered_dither_impl(
+        data,
+        width,
+        height,
+        params,
+        &ThresholdMap::from_fn(width as usize, height as usize, |x, y| screen.threshold(x, y)),
+    );
+}
+
+/// Straight line screen, like a single-direction engraving or a line halftone.
+pub struct LineScreenDither;
+impl DitherAlgorithm for LineScreenDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        line_screen_dither_impl(data, width, height, params, LinePattern::Straight);
+    }
+}
+
+/// Wavy line screen, the guilloché-like strokes found on banknotes.
+pub struct WavyLineDither;
+impl DitherAlgorithm for WavyLineDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        line_screen_dither_impl(data, width, height, params, LinePattern::Wavy);
+    }
+}
+
+/// Concentric circles around the image center; the screen angle has no effect.
+pub struct ConcentricCircleDither;
+impl DitherAlgorithm for ConcentricCircleDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        line_screen_dither_impl(data, width, height, params, LinePattern::Circles);
+    }
+}
+
+/// Crosshatching: line screens at `halftone_angle` and at right angles to it.
+pub struct CrosshatchDither;
+impl DitherAlgorithm for CrosshatchDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        line_screen_dither_impl(data, width, height, params, LinePattern::Crosshatch);
+    }
+}
+
+// === Stippling ===
+
+const DEFAULT_STIPPLE_ITERATIONS: u32 = 20;
+
+/// Dot radius in blocks that the automatic stipple count aims for.
+const DEFAULT_STIPPLE_RADIUS: f32 = 1.5;
+
+/// Stipples a preprocessed RGBA buffer at the resolution of its blocks, with dots drawn
+/// wherever the image has ink. A `stipple_count` of 0 places as many dots as it takes to
+/// cover that ink with dots of [`DEFAULT_STIPPLE_RADIUS`].
+fn stipple_blocks(data: &[u8], w: usize, h: usize, params: &DitherParams) -> Vec<stipple::Stipple> {
+    let bs = params.block_scale.max(1) as usize;
+    let mut gray = grayscale(data, params.luma, params.linear_light);
+    if bs > 1 {
+        gray = downsample_gray(&gray, w, h, bs);
+    }
+    let density: Vec<f32> = gray.iter().map(|v| 1.0 - (v / 255.0).clamp(0.0, 1.0)).collect();
+    let count = match params.stipple_count {
+        0 => {
+            let ink: f32 = density.iter().sum();
+            (ink / (std::f32::consts::PI * DEFAULT_STIPPLE_RADIUS * DEFAULT_STIPPLE_RADIUS)).round() as usize
+        }
+        n => n as usize,
+    };
+    stipple::weighted_voronoi_stipple(
+        &density,
+        w.div_ceil(bs),
+        h.div_ceil(bs),
+        count,
+        params.stipple_iterations,
+        params.seed,
+    )
+}
+
+/// Weighted Voronoi stippling: black dots of varying size, spaced evenly but as densely as
+/// the image is dark, like a pen-and-ink stipple drawing. With a palette, the dots are its
+/// darkest color on its lightest; gray levels are ignored.
+pub struct StippleDither;
+impl DitherAlgorithm for StippleDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+        let w = width as usize;
+        let h = height as usize;
+        let bs = params.block_scale.max(1) as usize;
+        let stipples = stipple_blocks(data, w, h, params);
+        let ink = stipple::render(&stipples, w.div_ceil(bs), h.div_ceil(bs));
+        if let Some(palette) = params.palette.as_ref() {
+            let brightness = |c: &&[f32; 3]| params.luma.apply(c[0], c[1], c[2]);
+            let by_brightness = |a: &&[f32; 3], b: &&[f32; 3]| brightness(a).total_cmp(&brightness(b));
+            let pen = *palette.colors().iter().min_by(by_brightness).unwrap();
+            let paper = *palette.colors().iter().max_by(by_brightness).unwrap();
+            let colors: Vec<[f32; 3]> = ink.iter().map(|&ink| if ink { pen } else { paper }).collect();
+            upscale_rgb(data, w, h, bs, &colors);
+            return;
+        }
+        let gray: Vec<f32> = ink.iter().map(|&ink| if ink { 0.0 } else { 255.0 }).collect();
+        write_gray(data, &gray, w, h, bs, params.linear_light);
+    }
+}
+
+// === Riemersma Dithering ===
+
+/// Number of recent errors carried along the curve.
+const RIEMERSMA_HISTORY: usize = 16;
+/// Weight of the newest error relative to the oldest one.
+const RIEMERSMA_RATIO: f32 = 16.0;
+
+/// Appends the pixels of one square block of a Hilbert curve to `order`, skipping those
+/// outside the `w`×`h` grid. The block has a corner at `origin` and is spanned by `a`, the
+/// direction the curve leaves it in, and `b`, the direction it first turns to; both have
+/// the block's side length. Blocks that lie wholly outside the grid are skipped without
+/// being walked.
+fn hilbert_block(
+    order: &mut Vec<usize>,
+    w: usize,
+    h: usize,
+    origin: (isize, isize),
+    a: (isize, isize),
+    b: (isize, isize),
+) {
+    let (x, y) = origin;
+    let (min_x, min_y) = (x.min(x + a.0 + b.0), y.min(y + a.1 + b.1));
+    if min_x >= w as isize || min_y >= h as isize {
+        return;
+    }
+    if a.0.abs() + a.1.abs() == 1 {
+        order.push(min_y as usize * w + min_x as usize);
+        return;
+    }
+    let (ha, hb) = ((a.0 / 2, a.1 / 2), (b.0 / 2, b.1 / 2));
+    hilbert_block(order, w, h, (x, y), hb, ha);
+    hilbert_block(order, w, h, (x + ha.0, y + ha.1), ha, hb);
+    hilbert_block(order, w, h, (x + ha.0 + hb.0, y + ha.1 + hb.1), ha, hb);
+    hilbert_block(order, w, h, (x + ha.0 + b.0, y + ha.1 + b.1), (-hb.0, -hb.1), (-ha.0, -ha.1));
+}
+
+/// Indices of a `w`×`h` grid in Hilbert-curve order. The curve fills the smallest
+/// enclosing power-of-two square and is clipped to the grid as it is built, so the quadrants
+/// outside a long, thin grid cost nothing.
+fn hilbert_order(w: usize, h: usize) -> impl Iterator<Item = usize> {
+    let n = w.max(h).max(1).next_power_of_two() as isize;
+    let mut order = Vec::with_capacity(w * h);
+    hilbert_block(&mut order, w, h, (0, 0), (n, 0), (0, n));
+    order.into_iter()
+}
+
+/// History weights from oldest to newest, growing exponentially and summing to 1 so every
+/// quantization error is passed on in full before it leaves the history.
+fn riemersma_weights() -> [f32; RIEMERSMA_HISTORY] {
+    let mut weights = [0.0; RIEMERSMA_HISTORY];
+    for (i, weight) in weights.iter_mut().enumerate() {
+        let age = (RIEMERSMA_HISTORY - 1 - i) as f32 / (RIEMERSMA_HISTORY - 1) as f32;
+        *weight = RIEMERSMA_RATIO.powf(-age);
+    }
+    let total: f32 = weights.iter().sum();
+    weights.map(|weight| weight / total)
+}
+
+/// Riemersma dithering of a grayscale buffer in place: pixels are visited along a Hilbert
+/// curve and each is offset by the weighted sum of the last few quantization errors.
+/// `edge_enhancement` and `reference` behave as in [`diffuse_gray`].
+fn riemersma_gray(
+    gray: &mut [f32],
+    w: usize,
+    h: usize,
+    threshold: f32,
+    levels: u32,
+    edge_enhancement: f32,
+    reference: Option<&FrameReference<1>>,
+) {
+    let modulation = threshold_modulation_gray(gray, w, h, edge_enhancement, reference);
+    let weights = riemersma_weights();
+    let mut history = [0.0f32; RIEMERSMA_HISTORY];
+    for idx in hilbert_order(w, h) {
+        let carried: f32 = history.iter().zip(&weights).map(|(e, w)| e * w).sum();
+        let old_pixel = gray[idx] + carried;
+        let new_pixel = quantize_gray(edge_enhanced(old_pixel, &modulation, idx), threshold, levels);
+        history.rotate_left(1);
+        history[RIEMERSMA_HISTORY - 1] = old_pixel - new_pixel;
+        gray[idx] = new_pixel;
+    }
+}
+
+/// Riemersma dithering against a palette, carrying per-channel errors in the working
+/// space of the palette's metric.
+fn riemersma_palette(
+    data: &mut [u8],
+    w: usize,
+    h: usize,
+    bs: usize,
+    palette: &Palette,
+    edge_enhancement: f32,
+    reference: Option<&FrameReference<3>>,
+) {
+    let new_w = w.div_ceil(bs);
+    let new_h = h.div_ceil(bs);
+    let mut colors = downsample_rgb(data, w, h, bs);
+    colors.par_iter_mut().for_each(|c| *c = palette.to_space(*c));
+    let modulation = with_temporal_modulation(edge_modulation(&colors, new_w, new_h, edge_enhancement), &colors, reference);
+    let weights = riemersma_weights();
+    let mut history = [[0.0f32; 3]; RIEMERSMA_HISTORY];
+    let mut output = vec![[0.0f32; 3]; colors.len()];
+    for idx in hilbert_order(new_w, new_h) {
+        let mut target = colors[idx];
+        for (error, &weight) in history.iter().zip(&weights) {
+            target[0] += error[0] * weight;
+            target[1] += error[1] * weight;
+            target[2] += error[2] * weight;
+        }
+        let index = palette.nearest_index(edge_enhanced_color(target, &modulation, idx));
+        let new_color = palette.space_color(index);
+        history.rotate_left(1);
+        history[RIEMERSMA_HISTORY - 1] = [
+            target[0] - new_color[0],
+            target[1] - new_color[1],
+            target[2] - new_color[2],
+        ];
+        output[idx] = palette.color(index);
+    }
+    upscale_rgb(data, w, h, bs, &output);
+}
+
+/// Riemersma dithering: error diffusion along a space-filling curve instead of row by row,
+/// which avoids the directional streaks of the raster-scan kernels.
+pub struct RiemersmaDither;
+impl DitherAlgorithm for RiemersmaDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+        let w = width as usize;
+        let h = height as usize;
+        let bs = params.block_scale.max(1) as usize;
+        if let Some(palette) = params.palette.as_ref() {
+            let reference = params.previous_frame.as_ref().map(|frame| frame.palette_reference(w, h, bs, palette));
+            riemersma_palette(data, w, h, bs, palette, params.edge_enhancement, reference.as_ref());
+            return;
+        }
+        let mut gray = grayscale(data, params.luma, params.linear_light);
+        if bs > 1 {
+            gray = downsample_gray(&gray, w, h, bs);
+        }
+        let reference = params
+            .previous_frame
+            .as_ref()
+            .map(|frame| frame.gray_reference(w, h, bs, params.luma, params.linear_light));
+        riemersma_gray(
+            &mut gray,
+            w.div_ceil(bs),
+            h.div_ceil(bs),
+            working_threshold(params.threshold, params.linear_light),
+            params.levels,
+            params.edge_enhancement,
+            reference.as_ref(),
+        );
+        write_gray(data, &gray, w, h, bs, params.linear_light);
+    }
+}
+
+// === Variable-Coefficient Error Diffusion ===
+
+// Ostromoukhov's (right, down-left, down, sum) weights for input levels 0–127, from the
+// table published with his paper; levels 128–255 mirror 127–0.
+const OSTROMOUKHOV_TABLE: [[u16; 4]; 128] = [
+    [13, 0, 5, 18], [13, 0, 5, 18], [21, 0, 10, 31], [7, 0, 4, 11],                     // 0–3
+    [8, 0, 5, 13], [47, 3, 28, 78], [23, 3, 13, 39], [15, 3, 8, 26],                    // 4–7
+    [22, 6, 11, 39], [43, 15, 20, 78], [7, 3, 3, 13], [501, 224, 211, 936],             // 8–11
+    [249, 116, 103, 468], [165, 80, 67, 312], [123, 62, 49, 234], [489, 256, 191, 936], // 12–15
+    [81, 44, 31, 156], [483, 272, 181, 936], [60, 35, 22, 117], [53, 32, 19, 104],      // 16–19
+    [237, 148, 83, 468], [471, 304, 161, 936], [3, 2, 1, 6], [481, 314, 185, 980],      // 20–23
+    [354, 226, 155, 735], [1389, 866, 685, 2940], [227, 138, 125, 490], [267, 158, 163, 588],// 24–27
+    [327, 188, 220, 735], [61, 34, 45, 140], [627, 338, 505, 1470], [1227, 638, 1075, 2940],// 28–31
+    [20, 10, 19, 49], [1937, 1000, 1767, 4704], [977, 520, 855, 2352], [657, 360, 551, 1568],// 32–35
+    [71, 40, 57, 168], [2005, 1160, 1539, 4704], [337, 200, 247, 784], [2039, 1240, 1425, 4704],// 36–39
+    [257, 160, 171, 588], [691, 440, 437, 1568], [1045, 680, 627, 2352], [301, 200, 171, 672],// 40–43
+    [177, 120, 95, 392], [2141, 1480, 1083, 4704], [1079, 760, 513, 2352], [725, 520, 323, 1568],// 44–47
+    [137, 100, 57, 294], [2209, 1640, 855, 4704], [53, 40, 19, 112], [2243, 1720, 741, 4704],// 48–51
+    [565, 440, 171, 1176], [759, 600, 209, 1568], [1147, 920, 285, 2352], [2311, 1880, 513, 4704],// 52–55
+    [97, 80, 19, 196], [335, 280, 57, 672], [1181, 1000, 171, 2352], [793, 680, 95, 1568],// 56–59
+    [599, 520, 57, 1176], [2413, 2120, 171, 4704], [405, 360, 19, 784], [2447, 2200, 57, 4704],// 60–63
+    [11, 10, 0, 21], [158, 151, 3, 312], [178, 179, 7, 364], [1030, 1091, 63, 2184],    // 64–67
+    [248, 277, 21, 546], [318, 375, 35, 728], [458, 571, 63, 1092], [878, 1159, 147, 2184],// 68–71
+    [5, 7, 1, 13], [172, 181, 37, 390], [97, 76, 22, 195], [72, 41, 17, 130],           // 72–75
+    [119, 47, 29, 195], [4, 1, 1, 6], [4, 1, 1, 6], [4, 1, 1, 6],                       // 76–79
+    [4, 1, 1, 6], [4, 1, 1, 6], [4, 1, 1, 6], [4, 1, 1, 6],                             // 80–83
+    [4, 1, 1, 6], [4, 1, 1, 6], [65, 18, 17, 100], [95, 29, 26, 150],                   // 84–87
+    [185, 62, 53, 300], [30, 11, 9, 50], [35, 14, 11, 60], [85, 37, 28, 150],           // 88–91
+    [55, 26, 19, 100], [80, 41, 29, 150], [155, 86, 59, 300], [5, 3, 2, 10],            // 92–95
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 96–99
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 100–103
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 104–107
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 108–111
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 112–115
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 116–119
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 120–123
+    [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10], [5, 3, 2, 10],                         // 124–127
+];
+
+// Zhou and Fang's re-optimized (right, down-left, down) weights at their key input levels
+// 0–127. Levels in between are linearly interpolated and 128–255 mirror 127–0.
+const ZHOU_FANG_KEY_LEVELS: &[(u8, [f32; 3])] = &[
+    (0, [13.0, 0.0, 5.0]),
+    (1, [1300249.0, 0.0, 499250.0]),
+    (2, [213113.0, 287.0, 99357.0]),
+    (3, [351854.0, 0.0, 199965.0]),
+    (4, [801100.0, 0.0, 490971.0]),
+    (10, [704075.0, 297466.0, 303694.0]),
+    (22, [46613.0, 31917.0, 21469.0]),
+    (32, [47482.0, 30617.0, 21900.0]),
+    (44, [43024.0, 42131.0, 14826.0]),
+    (64, [36411.0, 43477.0, 20112.0]),
+    (72, [38477.0, 53503.0, 8020.0]),
+    (77, [40503.0, 51000.0, 8497.0]),
+    (85, [35865.0, 34108.0, 30027.0]),
+    (95, [34117.0, 36899.0, 28983.0]),
+    (102, [35464.0, 35049.0, 29487.0]),
+    (107, [16477.0, 6501.0, 76998.0]),
+    (112, [33360.0, 37892.0, 28748.0]),
+    (127, [35269.0, 36066.0, 28665.0]),
+];
+
+// Zhou–Fang threshold modulation strength at key input levels 0–127, mirrored likewise.
+const ZHOU_FANG_STRENGTH_KEY_LEVELS: &[(u8, [f32; 1])] = &[
+    (0, [0.0]),
+    (44, [0.34]),
+    (64, [0.5]),
+    (85, [1.0]),
+    (95, [0.17]),
+    (102, [0.5]),
+    (107, [0.7]),
+    (112, [0.79]),
+    (127, [1.0]),
+];
+
+/// Expands values given at key levels 0–127 into a 256-entry table by linear interpolation,
+/// mirroring levels 128–255 onto 127–0.
+fn key_level_table<const N: usize>(keys: &[(u8, [f32; N])]) -> [[f32; N]; 256] {
+    let mut table = [[0.0; N]; 256];
+    for (level, entry) in table.iter_mut().enumerate() {
+        let tone = level.min(255 - level) as u8;
+        let upper = keys.iter().position(|&(k, _)| k >= tone).unwrap_or(keys.len() - 1);
+        let (k1, v1) = keys[upper];
+        let (k0, v0) = keys[upper.saturating_sub(1)];
+        let t = if k1 > k0 { (tone - k0) as f32 / (k1 - k0) as f32 } else { 0.0 };
+        *entry = std::array::from_fn(|i| v0[i] + (v1[i] - v0[i]) * t);
+    }
+    table
+}
+
+static OSTROMOUKHOV_COEFFICIENTS: Lazy<[[f32; 3]; 256]> = Lazy::new(|| {
+    std::array::from_fn(|level| {
+        let [right, down_left, down, sum] = OSTROMOUKHOV_TABLE[level.min(255 - level)].map(f32::from);
+        [right / sum, down_left / sum, down / sum]
+    })
+});
+
+static ZHOU_FANG_COEFFICIENTS: Lazy<[[f32; 3]; 256]> = Lazy::new(|| {
+    let normalized: Vec<(u8, [f32; 3])> = ZHOU_FANG_KEY_LEVELS
+        .iter()
+        .map(|&(level, weights)| {
+            let sum: f32 = weights.iter().sum();
+            (level, weights.map(|w| w / sum))
+        })
+        .collect();
+    key_level_table(&normalized)
+});
+
+static ZHOU_FANG_STRENGTH: Lazy<[[f32; 1]; 256]> = Lazy::new(|| key_level_table(ZHOU_FANG_STRENGTH_KEY_LEVELS));
+
+/// Which variable-coefficient error diffusion a pass runs.
+#[derive(Clone, Copy)]
+enum VariableDiffusion {
+    /// Ostromoukhov's coefficients.
+    Ostromoukhov,
+    /// Zhou and Fang's coefficients, with the threshold randomly perturbed by their
+    /// tone-dependent strength using this seed.
+    ZhouFang(u32),
+}
+
+impl VariableDiffusion {
+    fn coefficients(self) -> &'static [[f32; 3]; 256] {
+        match self {
+            VariableDiffusion::Ostromoukhov => &OSTROMOUKHOV_COEFFICIENTS,
+            VariableDiffusion::ZhouFang(_) => &ZHOU_FANG_COEFFICIENTS,
+        }
+    }
+}
+
+/// Tone used to look up the variable coefficients: the position of `value` between the two
+/// output levels around it, scaled to 0–255.
+#[inline(always)]
+fn coefficient_tone(value: f32, levels: u32) -> usize {
+    let step = 255.0 / (levels.clamp(2, 256) - 1) as f32;
+    let value = value.clamp(0.0, 255.0);
+    if value >= 255.0 {
+        return 255;
+    }
+    ((value / step).fract() * 255.0).round() as usize
+}
+
+/// Variable-coefficient error diffusion over a grayscale buffer in place: each pixel sends
+/// its error right, down-left and down with weights picked by its input tone. Rows are
+/// always scanned serpentine, which the coefficient tables were optimized for. The other
+/// settings of `params` and `reference` behave as in [`diffuse_gray`].
+fn diffuse_gray_variable(
+    gray: &mut [f32],
+    w: usize,
+    h: usize,
+    scheme: VariableDiffusion,
+    params: &DitherParams,
+    reference: Option<&FrameReference<1>>,
+) {
+    let source = gray.to_vec();
+    let threshold = working_threshold(params.threshold, params.linear_light);
+    let levels = params.levels;
+    let modulation = threshold_modulation_gray(gray, w, h, params.edge_enhancement, reference);
+    let coefficients = scheme.coefficients();
+    let mut rng = match scheme {
+        VariableDiffusion::ZhouFang(seed) => Some(XorShift32::new(seed)),
+        VariableDiffusion::Ostromoukhov => None,
+    };
+    let w = w as isize;
+    let h = h as isize;
+    for y in 0..h {
+        let reverse = y % 2 == 1;
+        let dir = if reverse { -1 } else { 1 };
+        for i in 0..w {
+            let x = if reverse { w - 1 - i } else { i };
+            let idx = (y * w + x) as usize;
+            let tone = coefficient_tone(source[idx], levels);
+            let pixel_threshold = match rng.as_mut() {
+                Some(rng) => threshold + (rng.next_f32() - 0.5) * 128.0 * ZHOU_FANG_STRENGTH[tone][0],
+                None => threshold,
+            };
+            let old_pixel = gray[idx];
+            let new_pixel = quantize_gray(edge_enhanced(old_pixel, &modulation, idx), pixel_threshold, levels);
+            let error = old_pixel - new_pixel;
+            gray[idx] = new_pixel;
+            let [right, down_left, down] = coefficients[tone];
+            for (dx, dy, factor) in [(dir, 0, right), (-dir, 1, down_left), (0, 1, down)] {
+                let nx = x + dx;
+                let ny = y + dy;
+                if nx >= 0 && nx < w && ny < h {
+                    gray[(ny * w + nx) as usize] += error * factor;
+                }
+            }
+        }
+    }
+}
+
+/// Variable-coefficient error diffusion against a palette. Coefficients are picked by the
+/// luma of each source color; the Zhou–Fang modulation does not apply, edge enhancement
+/// does.
+fn diffuse_palette_variable(
+    data: &mut [u8],
+    w: usize,
+    h: usize,
+    palette: &Palette,
+    scheme: VariableDiffusion,
+    params: &DitherParams,
+    reference: Option<&FrameReference<3>>,
+) {
+    let bs = params.block_scale.max(1) as usize;
+    let new_w = w.div_ceil(bs) as isize;
+    let new_h = h.div_ceil(bs) as isize;
+    let source = downsample_rgb(data, w, h, bs);
+    let mut colors: Vec<[f32; 3]> = source.par_iter().map(|&c| palette.to_space(c)).collect();
+    let modulation = with_temporal_modulation(
+        edge_modulation(&colors, new_w as usize, new_h as usize, params.edge_enhancement),
+        &colors,
+        reference,
+    );
+    let coefficients = scheme.coefficients();
+    let mut output = vec![[0.0f32; 3]; colors.len()];
+    for y in 0..new_h {
+        let reverse = y % 2 == 1;
+        let dir = if reverse { -1 } else { 1 };
+        for i in 0..new_w {
+            let x = if reverse { new_w - 1 - i } else { i };
+            let idx = (y * new_w + x) as usize;
+            let [r, g, b] = source[idx];
+            let tone = params.luma.apply(r, g, b).round().clamp(0.0, 255.0) as usize;
+            let old_color = colors[idx];
+            let index = palette.nearest_index(edge_enhanced_color(old_color, &modulation, idx));
+            let new_color = palette.space_color(index);
+            let error = [
+                old_color[0] - new_color[0],
+                old_color[1] - new_color[1],
+                old_color[2] - new_color[2],
+            ];
+            output[idx] = palette.color(index);
+            let [right, down_left, down] = coefficients[tone];
+            for (dx, dy, factor) in [(dir, 0, right), (-dir, 1, down_left), (0, 1, down)] {
+                let nx = x + dx;
+                let ny = y + dy;
+                if nx >= 0 && nx < new_w && ny < new_h {
+                    let target = &mut colors[(ny * new_w + nx) as usize];
+                    target[0] += error[0] * factor;
+                    target[1] += error[1] * factor;
+                    target[2] += error[2] * factor;
+                }
+            }
+        }
+    }
+    upscale_rgb(data, w, h, bs, &output);
+}
+
+fn variable_coefficient_dither(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    params: &DitherParams,
+    scheme: VariableDiffusion,
+) {
+    apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+    let w = width as usize;
+    let h = height as usize;
+    let bs = params.block_scale.max(1) as usize;
+    if let Some(palette) = params.palette.as_ref() {
+        let reference = params.previous_frame.as_ref().map(|frame| frame.palette_reference(w, h, bs, palette));
+        diffuse_palette_variable(data, w, h, palette, scheme, params, reference.as_ref());
+        return;
+    }
+    let mut gray = grayscale(data, params.luma, params.linear_light);
+    if bs > 1 {
+        gray = downsample_gray(&gray, w, h, bs);
+    }
+    let reference = params
+        .previous_frame
+        .as_ref()
+        .map(|frame| frame.gray_reference(w, h, bs, params.luma, params.linear_light));
+    diffuse_gray_variable(&mut gray, w.div_ceil(bs), h.div_ceil(bs), scheme, params, reference.as_ref());
+    write_gray(data, &gray, w, h, bs, params.linear_light);
+}
+
+/// Ostromoukhov's variable-coefficient error diffusion.
+pub struct OstromoukhovDither;
+impl DitherAlgorithm for OstromoukhovDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        variable_coefficient_dither(data, width, height, params, VariableDiffusion::Ostromoukhov);
+    }
+}
+
+/// Zhou–Fang: variable-coefficient error diffusion with their re-optimized coefficients
+/// and tone-dependent random threshold modulation, which breaks up the remaining mid-tone
+/// patterns.
+pub struct ZhouFangDither;
+impl DitherAlgorithm for ZhouFangDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        variable_coefficient_dither(data, width, height, params, VariableDiffusion::ZhouFang(params.seed));
+    }
+}
+
+// === Dot Diffusion ===
+
+// Knuth's class matrix: pixels are processed in class order and pass their error only to
+// neighbors of a higher class.
+const KNUTH_CLASS_MATRIX: [[u8; 8]; 8] = [
+    [34, 48, 40, 32, 29, 15, 23, 31],
+    [42, 58, 56, 53, 21, 5, 7, 10],
+    [50, 62, 61, 45, 13, 1, 2, 18],
+    [38, 46, 54, 37, 25, 17, 9, 26],
+    [28, 14, 22, 30, 35, 49, 41, 33],
+    [20, 4, 6, 11, 43, 59, 57, 52],
+    [12, 0, 3, 19, 51, 63, 60, 44],
+    [24, 16, 8, 27, 39, 47, 55, 36],
+];
+
+/// Position of each class within the class matrix.
+fn class_positions() -> [(usize, usize); 64] {
+    let mut positions = [(0, 0); 64];
+    for (y, row) in KNUTH_CLASS_MATRIX.iter().enumerate() {
+        for (x, &class) in row.iter().enumerate() {
+            positions[class as usize] = (y, x);
+        }
+    }
+    positions
+}
+
+/// Most neighbors [`dot_diffusion_neighbors`] can return: the 24 cells of a 7×7 ring.
+const MAX_DOT_NEIGHBORS: usize = 24;
+
+/// Neighbors of `(x, y)` that are processed later, with Knuth's weights (2 for edge
+/// neighbors, 1 for diagonal ones). Returns the count written into `out` and the weight sum.
+///
+/// A baron, a pixel none of whose neighbors comes later, would drop its error; instead it
+/// passes the error to the nearest later pixels in rings of growing size, weighted by
+/// 2 / distance² like the direct neighbors. The rings stay within the offsets -3..=4, an
+/// 8×8 window, so pixels of one class never share a target. Only the last class of each
+/// tile, and pixels cut off by the image border, find no later pixel at all.
+#[inline(always)]
+fn dot_diffusion_neighbors(
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+    out: &mut [(usize, f32); MAX_DOT_NEIGHBORS],
+) -> (usize, f32) {
+    let class = KNUTH_CLASS_MATRIX[y % 8][x % 8];
+    let mut count = 0;
+    let mut total = 0.0;
+    for ring in 1isize..=4 {
+        for dy in -ring.min(3)..=ring {
+            for dx in -ring.min(3)..=ring {
+                let nx = x as isize + dx;
+                let ny = y as isize + dy;
+                if dx.abs().max(dy.abs()) != ring || nx < 0 || ny < 0 || nx >= w as isize || ny >= h as isize {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                if KNUTH_CLASS_MATRIX[ny % 8][nx % 8] > class {
+                    let weight = 2.0 / (dx * dx + dy * dy) as f32;
+                    out[count] = (ny * w + nx, weight);
+                    count += 1;
+                    total += weight;
+                }
+            }
+        }
+        if count > 0 {
+            break;
+        }
+    }
+    (count, total)
+}
+
+/// Dot diffusion of a grayscale buffer in place. Pixels of one class are 8 apart, so their
+/// neighborhoods never overlap and each class is processed in parallel. `edge_enhancement`
+/// behaves as in [`diffuse_gray`].
+fn dot_diffuse_gray(
+    gray: &mut [f32],
+    w: usize,
+    h: usize,
+    threshold: f32,
+    levels: u32,
+    edge_enhancement: f32,
+    reference: Option<&FrameReference<1>>,
+) {
+    let modulation = threshold_modulation_gray(gray, w, h, edge_enhancement, reference);
+    let gray_ptr = gray.as_mut_ptr() as usize;
+    for (cy, cx) in class_positions() {
+        (cy..h).into_par_iter().step_by(8).for_each(|y| {
+            let mut neighbors = [(0, 0.0); MAX_DOT_NEIGHBORS];
+            for x in (cx..w).step_by(8) {
+                unsafe {
+                    let ptr = gray_ptr as *mut f32;
+                    let old_pixel = *ptr.add(y * w + x);
+                    let (count, total) = dot_diffusion_neighbors(x, y, w, h, &mut neighbors);
+                    // Nothing can take this pixel's error, so keep it as small as possible.
+                    let new_pixel = if count == 0 {
+                        quantize_gray(old_pixel, 127.5, levels)
+                    } else {
+                        quantize_gray(edge_enhanced(old_pixel, &modulation, y * w + x), threshold, levels)
+                    };
+                    *ptr.add(y * w + x) = new_pixel;
+                    for &(idx, weight) in &neighbors[..count] {
+                        *ptr.add(idx) += (old_pixel - new_pixel) * weight / total;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Dot diffusion against a palette, diffusing per-channel errors in the working space of
+/// the palette's metric.
+fn dot_diffuse_palette(
+    data: &mut [u8],
+    w: usize,
+    h: usize,
+    bs: usize,
+    palette: &Palette,
+    edge_enhancement: f32,
+    reference: Option<&FrameReference<3>>,
+) {
+    let new_w = w.div_ceil(bs);
+    let new_h = h.div_ceil(bs);
+    let mut colors = downsample_rgb(data, w, h, bs);
+    colors.par_iter_mut().for_each(|c| *c = palette.to_space(*c));
+    let modulation = with_temporal_modulation(edge_modulation(&colors, new_w, new_h, edge_enhancement), &colors, reference);
+    let mut output = vec![[0.0f32; 3]; colors.len()];
+    let colors_ptr = colors.as_mut_ptr() as usize;
+    let output_ptr = output.as_mut_ptr() as usize;
+    for (cy, cx) in class_positions() {
+        (cy..new_h).into_par_iter().step_by(8).for_each(|y| {
+            let mut neighbors = [(0, 0.0); MAX_DOT_NEIGHBORS];
+            for x in (cx..new_w).step_by(8) {
+                unsafe {
+                    let colors = colors_ptr as *mut [f32; 3];
+                    let old_color = *colors.add(y * new_w + x);
+                    let (count, total) = dot_diffusion_neighbors(x, y, new_w, new_h, &mut neighbors);
+                    let index = if count == 0 {
+                        palette.nearest_index(old_color)
+                    } else {
+                        palette.nearest_index(edge_enhanced_color(old_color, &modulation, y * new_w + x))
+                    };
+                    let new_color = palette.space_color(index);
+                    *(output_ptr as *mut [f32; 3]).add(y * new_w + x) = palette.color(index);
+                    for &(idx, weight) in &neighbors[..count] {
+                        let target = &mut *colors.add(idx);
+                        for c in 0..3 {
+                            target[c] += (old_color[c] - new_color[c]) * weight / total;
+                        }
+                    }
+                }
+            }
+        });
+    }
+    upscale_rgb(data, w, h, bs, &output);
+}
+
+/// Knuth's dot diffusion: error-diffusion-like output where every pixel of a class can be
+/// processed at once. The error that reaches the last class of a tile is dropped, so the
+/// threshold is taken as a fraction of the step between gray levels even in linear light:
+/// a threshold away from mid-step would let error of one sign pile up there and shift the
+/// tone.
+pub struct DotDiffusionDither;
+impl DitherAlgorithm for DotDiffusionDither {
+    fn apply(&self, data: &mut [u8], width: u32, height: u32, params: &DitherParams) {
+        apply_preprocessing(data, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+        let w = width as usize;
+        let h = height as usize;
+        let bs = params.block_scale.max(1) as usize;
+        if let Some(palette) = params.palette.as_ref() {
+            let reference = params.previous_frame.as_ref().map(|frame| frame.palette_reference(w, h, bs, palette));
+            dot_diffuse_palette(data, w, h, bs, palette, params.edge_enhancement, reference.as_ref());
+            return;
+        }
+        let mut gray = grayscale(data, params.luma, params.linear_light);
+        if bs > 1 {
+            gray = downsample_gray(&gray, w, h, bs);
+        }
+        let reference = params
+            .previous_frame
+            .as_ref()
+            .map(|frame| frame.gray_reference(w, h, bs, params.luma, params.linear_light));
+        dot_diffuse_gray(
+            &mut gray,
+            w.div_ceil(bs),
+            h.div_ceil(bs),
+            params.threshold as f32,
+            params.levels,
+            params.edge_enhancement,
+            reference.as_ref(),
+        );
+        write_gray(data, &gray, w, h, bs, params.linear_light);
+    }
+}
+
+// Error diffusion kernels (e.g., SIERRA_KERNEL, SIERRA_TWO_ROW_KERNEL, SIERRA_LITE_KERNEL) are defined elsewhere in the code.
+
+static ALGORITHM_REGISTRY: Lazy<HashMap<&'static str, Box<dyn DitherAlgorithm>>> = Lazy::new(|| {
+    let mut m: HashMap<&'static str, Box<dyn DitherAlgorithm>> = HashMap::new();
+    m.insert("threshold", Box::new(ThresholdDither));
+    m.insert("floyd-steinberg", Box::new(FloydSteinbergDither));
+    m.insert("bayer", Box::new(BayerDither));
+    m.insert("bayer-recursive", Box::new(RecursiveBayerDither));
+    m.insert("knoll", Box::new(KnollDither));
+    m.insert("yliluoma", Box::new(YliluomaDither));
+    m.insert("blue-noise", Box::new(BlueNoiseDither));
+    m.insert("white-noise", Box::new(WhiteNoiseDither));
+    m.insert("triangular-noise", Box::new(TriangularNoiseDither));
+    m.insert("interleaved-gradient-noise", Box::new(InterleavedGradientNoiseDither));
+    m.insert("halftone", Box::new(HalftoneDither));
+    m.insert("cmyk-halftone", Box::new(CmykHalftoneDither));
+    m.insert("line-screen", Box::new(LineScreenDither));
+    m.insert("wavy-lines", Box::new(WavyLineDither));
+    m.insert("concentric-circles", Box::new(ConcentricCircleDither));
+    m.insert("crosshatch", Box::new(CrosshatchDither));
+    m.insert("stipple", Box::new(StippleDither));
+    m.insert("jarvis", Box::new(JarvisJudiceNinkeDither));
+    m.insert("atkinson", Box::new(AtkinsonDither));
+    m.insert("sierra", Box::new(SierraDither));
+    m.insert("sierra-two-row", Box::new(SierraTwoRowDither));
+    m.insert("sierra-lite", Box::new(SierraLiteDither));
+    m.insert("stucki", Box::new(StuckiDither));
+    m.insert("burkes", Box::new(BurkesDither));
+    m.insert("fan", Box::new(FanDither));
+    m.insert("shiau-fan", Box::new(ShiauFanDither));
+    m.insert("shiau-fan-2", Box::new(ShiauFan2Dither));
+    m.insert("false-floyd-steinberg", Box::new(FalseFloydSteinbergDither));
+    m.insert("riemersma", Box::new(RiemersmaDither));
+    m.insert("ostromoukhov", Box::new(OstromoukhovDither));
+    m.insert("zhou-fang", Box::new(ZhouFangDither));
+    m.insert("dot-diffusion", Box::new(DotDiffusionDither));
+    m
+});
+
+// === User-Defined Kernels ===
+
+// Largest offset a custom kernel may reach in either direction.
+const MAX_CUSTOM_KERNEL_REACH: isize = 16;
+
+type CustomKernel = Vec<(isize, isize, f32)>;
+
+/// Kernels registered from JavaScript, selectable by name in `apply_dither`.
+static CUSTOM_KERNELS: Lazy<RwLock<HashMap<String, CustomKernel>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Validates packed `[dx, dy, weight, ...]` triplets and divides the weights by `divisor`
+/// (or by their sum when `divisor` is 0). Offsets must be whole numbers that point forward
+/// in scan order: to the right on the current row, or onto a later row.
+fn parse_custom_kernel(kernel: &[f32], divisor: f32) -> Result<CustomKernel, String> {
+    if kernel.is_empty() || !kernel.len().is_multiple_of(3) {
+        return Err("kernel must be a non-empty list of (dx, dy, weight) triplets".into());
+    }
+    let divisor = if divisor == 0.0 {
+        kernel.chunks_exact(3).map(|t| t[2]).sum()
+    } else {
+        divisor
+    };
+    if !divisor.is_finite() || divisor == 0.0 {
+        return Err("kernel divisor must be a finite, non-zero number".into());
+    }
+    kernel
+        .chunks_exact(3)
+        .map(|t| {
+            let (dx, dy, weight) = (t[0], t[1], t[2]);
+            if dx.fract() != 0.0 || dy.fract() != 0.0 || !weight.is_finite() {
+                return Err(format!("invalid kernel entry ({dx}, {dy}, {weight})"));
+            }
+            let (dx, dy) = (dx as isize, dy as isize);
+            if dx.abs() > MAX_CUSTOM_KERNEL_REACH || dy > MAX_CUSTOM_KERNEL_REACH {
+                return Err(format!("kernel offset ({dx}, {dy}) is out of range"));
+            }
+            if dy < 0 || (dy == 0 && dx <= 0) {
+                return Err(format!("kernel offset ({dx}, {dy}) does not point forward in scan order"));
+            }
+            Ok((dx, dy, weight / divisor))
+        })
+        .collect()
+}
+
+/// Registers an error-diffusion kernel under `name` so it can be passed to `apply_dither`
+/// like a built-in algorithm. `kernel` holds packed `[dx, dy, weight, ...]` triplets;
+/// weights are divided by `divisor`, or by their sum if `divisor` is 0. Registering an
+/// existing custom name replaces it; built-in algorithm names are rejected.
+#[wasm_bindgen]
+pub fn register_kernel(name: &str, kernel: &[f32], divisor: f32) -> Result<(), JsError> {
+    if ALGORITHM_REGISTRY.contains_key(name) {
+        return Err(JsError::new(&format!("\"{name}\" is a built-in algorithm")));
+    }
+    let kernel = parse_custom_kernel(kernel, divisor).map_err(|e| JsError::new(&e))?;
+    CUSTOM_THRESHOLD_MAPS.write().unwrap().remove(name);
+    CUSTOM_KERNELS.write().unwrap().insert(name.to_string(), kernel);
+    Ok(())
+}
+
+// === User-Defined Threshold Maps ===
+
+/// Threshold maps registered from JavaScript, selectable by name in `apply_dither`.
+static CUSTOM_THRESHOLD_MAPS: Lazy<RwLock<HashMap<String, Arc<ThresholdMap>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Validates and stores a threshold map. Replaces an existing custom kernel or map of the
+/// same name; built-in algorithm names are rejected.
+fn insert_threshold_map(name: &str, levels: Vec<u32>, width: u32, height: u32) -> Result<(), JsError> {
+    if ALGORITHM_REGISTRY.contains_key(name) {
+        return Err(JsError::new(&format!("\"{name}\" is a built-in algorithm")));
+    }
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 || levels.len() != w * h {
+        return Err(JsError::new(&format!(
+            "threshold map has {} entries, expected {width}x{height}",
+            levels.len()
+        )));
+    }
+    let map = Arc::new(ThresholdMap::from_levels(w, h, &levels));
+    CUSTOM_KERNELS.write().unwrap().remove(name);
+    CUSTOM_THRESHOLD_MAPS.write().unwrap().insert(name.to_string(), map);
+    Ok(())
+}
+
+/// Registers a threshold map under `name` so it can be passed to `apply_dither` like a
+/// built-in algorithm. `values` holds `width * height` row-major 16-bit levels; they are
+/// scaled by the largest entry, so rank matrices and full-range textures both work.
+#[wasm_bindgen]
+pub fn register_threshold_map(name: &str, values: &[u16], width: u32, height: u32) -> Result<(), JsError> {
+    insert_threshold_map(name, values.iter().map(|&v| v as u32).collect(), width, height)
+}
+
+/// 8-bit variant of [`register_threshold_map`].
+#[wasm_bindgen]
+pub fn register_threshold_map_u8(name: &str, values: &[u8], width: u32, height: u32) -> Result<(), JsError> {
+    insert_threshold_map(name, values.iter().map(|&v| v as u32).collect(), width, height)
+}
+
+/// Registers a threshold map from an RGBA image, such as a texture PNG drawn to a canvas.
+/// Threshold textures are grayscale, so the red channel of each pixel is used as its level
+/// as-is, whatever luma model the image is later dithered with; alpha is ignored.
+#[wasm_bindgen]
+pub fn register_threshold_map_image(name: &str, rgba: &[u8], width: u32, height: u32) -> Result<(), JsError> {
+    insert_threshold_map(name, rgba.chunks_exact(4).map(|c| c[0] as u32).collect(), width, height)
+}
+
+/// Reads C, M, Y, K screen angles, falling back to the conventional ones unless four
+/// values are given.
+fn parse_screen_angles(angles: Option<&[f32]>) -> [f32; 4] {
+    match angles {
+        Some(&[c, m, y, k, ..]) => [c, m, y, k],
+        _ => halftone::CMYK_ANGLES,
+    }
+}
+
+/// An opaque copy of an RGBA buffer with every pixel composited over white paper, for the
+/// exports that print ink.
+fn composite_over_white(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4)
+        .flat_map(|c| {
+            let a = c[3] as f32 / 255.0;
+            let over_white = |v: u8| (255.0 - (255.0 - v as f32) * a).round() as u8;
+            [over_white(c[0]), over_white(c[1]), over_white(c[2]), 255]
+        })
+        .collect()
+}
+
+/// Returns the four 1-bit ink plates of the `"cmyk-halftone"` algorithm, plane by plane in
+/// C, M, Y, K order, each `width * height` bytes with 1 where ink is printed. Transparent
+/// pixels are composited over white paper first, so they receive no ink. `options` are the
+/// ones passed to `apply_dither`; the palette settings are ignored.
+#[wasm_bindgen]
+pub fn cmyk_plates(data: &[u8], width: u32, height: u32, options: &DitherOptions) -> Vec<u8> {
+    let mut image = composite_over_white(data);
+    let params = options.params(None, None);
+    apply_preprocessing(&mut image, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+    let w = width as usize;
+    let h = height as usize;
+    let bs = params.block_scale.max(1) as usize;
+    let plates = cmyk_halftone_plates(&image, w, h, &params);
+    // Expand the per-block plates back to one byte per pixel.
+    let new_w = w.div_ceil(bs);
+    let block_plane = plates.len() / 4;
+    (0..4)
+        .flat_map(|ink| (0..h).flat_map(move |y| (0..w).map(move |x| (ink, y, x))))
+        .map(|(ink, y, x)| plates[ink * block_plane + (y / bs) * new_w + x / bs])
+        .collect()
+}
+
+/// Returns the dots of the `"stipple"` algorithm as `x, y, radius` triples in pixel
+/// coordinates, e.g. for drawing them as vector shapes. Transparent pixels are composited
+/// over white paper first, so they receive no dots. `options` are the ones passed to
+/// `apply_dither`; the palette settings are ignored.
+#[wasm_bindgen]
+pub fn stipple_points(data: &[u8], width: u32, height: u32, options: &DitherOptions) -> Vec<f32> {
+    let mut image = composite_over_white(data);
+    let params = options.params(None, None);
+    apply_preprocessing(&mut image, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+    let bs = params.block_scale.max(1) as usize;
+    let stipples = stipple_blocks(&image, width as usize, height as usize, &params);
+    let scale = bs as f32;
+    stipples.iter().flat_map(|s| [s.x * scale, s.y * scale, s.radius * scale]).collect()
+}
+
+/// Refines a black-and-white dither result with direct binary search, for final renders
+/// where speed does not matter. `original` is the source image and `halftone` the output of
+/// `apply_dither` for it, which is updated in place, and `options` the ones that produced
+/// it; the preprocessing, block scale, luma and linear-light settings are used. With a
+/// block scale above 1 the search toggles whole blocks, so the result keeps its blocky look.
+/// `progress(passes_done, max_iterations)` is called after every pass and may return
+/// `false` to stop early. Returns the number of passes run. The search only handles
+/// black-and-white results, so options that select a palette or more than two gray levels
+/// are rejected with an error.
+#[wasm_bindgen]
+pub fn dbs_refine(
+    original: &[u8],
+    halftone: &mut [u8],
+    width: u32,
+    height: u32,
+    options: &DitherOptions,
+    max_iterations: u32,
+    progress: Option<js_sys::Function>,
+) -> Result<u32, JsError> {
+    if options.levels > 2 || options.resolve_palette(original).is_some() {
+        return Err(JsError::new("dbs_refine only refines black-and-white results"));
+    }
+    let params = options.params(None, None);
+    let mut image = original.to_vec();
+    apply_preprocessing(&mut image, width, height, params.contrast, params.gamma, params.pixelation, params.blur);
+    let w = width as usize;
+    let h = height as usize;
+    let bs = params.block_scale.max(1) as usize;
+    let mut gray = grayscale(&image, params.luma, params.linear_light);
+    if bs > 1 {
+        gray = downsample_gray(&gray, w, h, bs);
+    }
+    gray.iter_mut().for_each(|v| *v /= 255.0);
+    let mut binary: Vec<bool> = downsample_rgb(halftone, w, h, bs).iter().map(|c| c[0] >= 127.5).collect();
+    let new_w = w.div_ceil(bs);
+    let passes = dbs::direct_binary_search(
+        &gray,
+        &mut binary,
+        new_w,
+        h.div_ceil(bs),
+        max_iterations,
+        |done, total| {
+            progress.as_ref().is_none_or(|callback| {
+                callback
+                    .call2(&JsValue::NULL, &JsValue::from(done), &JsValue::from(total))
+                    .map_or(true, |result| result.as_bool() != Some(false))
+            })
+        },
+    );
+    // Transparent pixels were restored from the source by `apply_dither`; leave them be.
+    for (i, chunk) in halftone.chunks_exact_mut(4).enumerate() {
+        let white = binary[(i / w / bs) * new_w + (i % w) / bs];
+        if chunk[3] == 255 {
+            let value = if white { 255 } else { 0 };
+            chunk[0] = value;
+            chunk[1] = value;
+            chunk[2] = value;
+        }
+    }
+    Ok(passes)
+}
+
+/// Picks the palette for a call: explicit RGB bytes win over a named preset, which wins
+/// over a palette extracted from the image itself (k-means seeded with `seed`).
+/// Returns `None` (black and white output) if none of them yields a palette.
+fn resolve_palette(
+    custom: Option<&[u8]>,
+    preset: Option<&str>,
+    extract: Option<&str>,
+    extract_size: u32,
+    seed: u32,
+    data: &[u8],
+    metric: ColorMetric,
+) -> Option<Palette> {
+    custom
+        .and_then(Palette::from_rgb_bytes)
+        .or_else(|| preset.and_then(|name| PALETTE_REGISTRY.get(name).cloned()))
+        .or_else(|| extract.and_then(|method| quantize::extract_palette(method, data, extract_size as usize, seed)))
+        .map(|palette| palette.with_metric(metric))
+}
+
+/// Analyses an RGBA buffer and returns an N-color palette as packed RGB triplets.
+/// `method` is `"median-cut"`, `"kmeans"` or `"octree"`; `seed` only affects k-means.
+/// The result can be passed straight back to `apply_dither` as its custom palette.
+#[wasm_bindgen]
+pub fn extract_palette(method: &str, data: &[u8], colors: u32, seed: u32) -> Vec<u8> {
+    quantize::extract_palette(method, data, colors as usize, seed)
+        .map(|palette| palette.to_rgb_bytes())
+        .unwrap_or_default()
+}
+
+/// Returns the packed RGB triplets of a named palette preset, e.g. for drawing swatches.
+#[wasm_bindgen]
+pub fn get_palette_preset(name: &str) -> Option<Vec<u8>> {
+    PALETTE_REGISTRY.get(name).map(Palette::to_rgb_bytes)
+}
+
+// === Dither Options ===
+
+/// Settings for [`apply_dither`], [`FrameDitherer::dither`], [`cmyk_plates`],
+/// [`stipple_points`] and [`dbs_refine`]. `new DitherOptions()` starts from neutral
+/// defaults (no preprocessing, threshold 128, a 4×4 Bayer matrix, black and white output);
+/// set the fields that differ before passing it in. Unset optional fields fall back to
+/// each algorithm's default.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct DitherOptions {
+    pub threshold: u8,
+    /// Contrast in percent; 100 leaves the image unchanged.
+    pub contrast: f32,
+    pub gamma: f32,
+    pub pixelation: u32,
+    pub blur: u32,
+    pub block_scale: u32,
+    pub bayer_width: u8,
+    pub bayer_height: u8,
+    /// Custom palette as packed RGB triplets; wins over `palette_preset`, which wins over
+    /// `palette_extract`.
+    pub palette: Option<Vec<u8>>,
+    /// Name of a built-in palette, see `get_palette_preset`.
+    pub palette_preset: Option<String>,
+    /// Extracts a `palette_size`-color palette from each image with this method, as
+    /// `extract_palette` does with `seed`.
+    pub palette_extract: Option<String>,
+    pub palette_size: u32,
+    /// Metric used to pick the nearest palette color.
+    pub color_metric: Option<String>,
+    /// Dither in linear light: sRGB input is decoded before quantization and the output
+    /// re-encoded, so the average of the dithered pixels matches the source brightness.
+    pub linear_light: bool,
+    /// Number of evenly spaced gray levels (2–256) for black-and-white output.
+    pub levels: u32,
+    /// Luma model name; `luma_weights`, if given, overrides it.
+    pub luma_model: Option<String>,
+    pub luma_weights: Option<Vec<f32>>,
+    pub serpentine: bool,
+    pub noise_size: u32,
+    pub seed: u32,
+    pub halftone_cell: Option<f32>,
+    pub halftone_angle: Option<f32>,
+    pub dot_shape: Option<String>,
+    /// C, M, Y and K screen angles of the CMYK halftone.
+    pub screen_angles: Option<Vec<f32>>,
+    pub black_generation: Option<f32>,
+    pub under_color_removal: Option<f32>,
+    pub edge_enhancement: Option<f32>,
+    pub stipple_count: u32,
+    pub stipple_iterations: Option<u32>,
+}
+
+impl Default for DitherOptions {
+    fn default() -> Self {
+        DitherOptions {
+            threshold: 128,
+            contrast: 100.0,
+            gamma: 1.0,
+            pixelation: 0,
+            blur: 0,
+            block_scale: 1,
+            bayer_width: 4,
+            bayer_height: 4,
+            palette: None,
+            palette_preset: None,
+            palette_extract: None,
+            palette_size: 0,
+            color_metric: None,
+            linear_light: false,
+            levels: 2,
+            luma_model: None,
+            luma_weights: None,
+            serpentine: false,
+            noise_size: 0,
+            seed: 0,
+            halftone_cell: None,
+            halftone_angle: None,
+            dot_shape: None,
+            screen_angles: None,
+            black_generation: None,
+            under_color_removal: None,
+            edge_enhancement: None,
+            stipple_count: 0,
+            stipple_iterations: None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DitherOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> DitherOptions {
+        DitherOptions::default()
+    }
+}
+
+impl DitherOptions {
+    /// The output palette picked by these options for `image`, if any.
+    fn resolve_palette(&self, image: &[u8]) -> Option<Palette> {
+        resolve_palette(
+            self.palette.as_deref(),
+            self.palette_preset.as_deref(),
+            self.palette_extract.as_deref(),
+            self.palette_size,
+            self.seed,
+            image,
+            self.color_metric.as_deref().map(ColorMetric::from_name).unwrap_or_default(),
+        )
+        .map(|palette| palette.with_linear_light(self.linear_light))
+    }
+
+    /// The parameters handed to the algorithms, with defaults filled in.
+    fn params(&self, palette: Option<Palette>, previous_frame: Option<PreviousFrame>) -> DitherParams {
+        DitherParams {
+            threshold: self.threshold,
+            contrast: self.contrast,
+            gamma: self.gamma,
+            pixelation: self.pixelation,
+            blur: self.blur,
+            block_scale: self.block_scale,
+            bayer_width: self.bayer_width,
+            bayer_height: self.bayer_height,
+            palette,
+            linear_light: self.linear_light,
+            levels: self.levels,
+            luma: LumaModel::from_name(self.luma_model.as_deref().unwrap_or_default(), self.luma_weights.as_deref()),
+            serpentine: self.serpentine,
+            noise_size: self.noise_size,
+            seed: self.seed,
+            halftone_cell: self.halftone_cell.unwrap_or(DEFAULT_HALFTONE_CELL),
+            halftone_angle: self.halftone_angle.unwrap_or(DEFAULT_HALFTONE_ANGLE),
+            dot_shape: self.dot_shape.as_deref().map(DotShape::from_name).unwrap_or_default(),
+            screen_angles: parse_screen_angles(self.screen_angles.as_deref()),
+            black_generation: self.black_generation.unwrap_or(DEFAULT_BLACK_GENERATION),
+            under_color_removal: self.under_color_removal.unwrap_or(DEFAULT_UNDER_COLOR_REMOVAL),
+            edge_enhancement: self.edge_enhancement.unwrap_or(0.0).max(-1.0),
+            previous_frame,
+            stipple_count: self.stipple_count,
+            stipple_iterations: self.stipple_iterations.unwrap_or(DEFAULT_STIPPLE_ITERATIONS),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn apply_dither(algorithm: &str, data: &mut [u8], width: u32, height: u32, options: &DitherOptions) {
+    dither_frame(algorithm, data, width, height, options, None);
+}
+
+/// Body of [`apply_dither`], also used by [`FrameDitherer`] to pass in the previous frame.
+fn dither_frame(
+    algorithm: &str,
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    options: &DitherOptions,
+    previous_frame: Option<PreviousFrame>,
+) {
+    // Step 1: Backup original RGBA data and resolve the output palette from it.
+    let backup = data.to_vec();
+    let palette = options.resolve_palette(&backup);
+
+    // Step 2: Premultiply RGB channels by alpha.
+    for chunk in data.chunks_exact_mut(4) {
+        let a = chunk[3] as f32 / 255.0;
+        chunk[0] = (chunk[0] as f32 * a).round() as u8;
+        chunk[1] = (chunk[1] as f32 * a).round() as u8;
+        chunk[2] = (chunk[2] as f32 * a).round() as u8;
+        // Alpha remains unchanged.
+    }
+
+    // Step 3: Apply selected dithering algorithm.
+    let params = options.params(palette, previous_frame);
+    if let Some(alg) = ALGORITHM_REGISTRY.get(algorithm) {
+         alg.apply(data, width, height, &params);
+    } else if let Some(kernel) = CUSTOM_KERNELS.read().unwrap().get(algorithm) {
+         apply_kernel_dither(data, width, height, &params, kernel);
+    } else if let Some(map) = CUSTOM_THRESHOLD_MAPS.read().unwrap().get(algorithm) {
+         ordered_dither_impl(data, width, height, &params, map);
+    } else {
+         // Fallback to threshold dithering if algorithm not found.
+         ThresholdDither.apply(data, width, height, &params);
+    }
+
+    // Step 4: Un-premultiply RGB channels.
+    for chunk in data.chunks_exact_mut(4) {
+        let alpha = chunk[3];
+        if alpha > 0 {
+            let inv = 255.0 / (alpha as f32);
+            chunk[0] = ((chunk[0] as f32 * inv).round()).min(255.0) as u8;
+            chunk[1] = ((chunk[1] as f32 * inv).round()).min(255.0) as u8;
+            chunk[2] = ((chunk[2] as f32 * inv).round()).min(255.0) as u8;
+        }
+    }
+
+    // Step 5: Restore original RGBA for non-opaque pixels.
+    for (i, chunk) in data.chunks_exact_mut(4).enumerate() {
+        let orig_alpha = backup[i * 4 + 3];
+        if orig_alpha != 255 {
+            chunk[0] = backup[i * 4];
+            chunk[1] = backup[i * 4 + 1];
+            chunk[2] = backup[i * 4 + 2];
+            chunk[3] = orig_alpha;
+        }
+    }
+}
+
+// === Frame Sequences ===
+
+/// Default pull of the error-diffusion algorithms towards the previous frame's output.
+const DEFAULT_TEMPORAL_STABILITY: f32 = 0.5;
+/// Default per-channel input change treated as sensor noise.
+const DEFAULT_NOISE_TOLERANCE: u32 = 4;
+/// Input change (per channel, 0–255) at which the pull towards the previous frame has
+/// faded out completely.
+const TEMPORAL_MOTION_RANGE: f32 = 32.0;
+
+/// The previous frame of a sequence, kept by [`FrameDitherer`] so the error-diffusion
+/// algorithms can stay close to last frame's pattern where the picture has not changed.
+pub struct PreviousFrame {
+    /// Final RGBA output of the previous frame.
+    output: Vec<u8>,
+    /// Per-pixel pull towards `output`, 0–1.
+    weights: Vec<f32>,
+}
+
+impl PreviousFrame {
+    /// The previous output as gray levels on the `bs` block grid, in the working domain.
+    fn gray_reference(&self, w: usize, h: usize, bs: usize, luma: LumaModel, linear_light: bool) -> FrameReference<1> {
+        let output = downsample_gray(&grayscale(&self.output, luma, linear_light), w, h, bs);
+        FrameReference {
+            output: output.into_iter().map(|v| [v]).collect(),
+            weights: downsample_gray(&self.weights, w, h, bs),
+        }
+    }
+
+    /// The previous output on the `bs` block grid, in the working space of `palette`.
+    fn palette_reference(&self, w: usize, h: usize, bs: usize, palette: &Palette) -> FrameReference<3> {
+        FrameReference {
+            output: downsample_rgb(&self.output, w, h, bs).into_par_iter().map(|c| palette.to_space(c)).collect(),
+            weights: downsample_gray(&self.weights, w, h, bs),
+        }
+    }
+}
+
+/// Dithers a sequence of frames (webcam, video, GIF) so the pattern stays put from frame to
+/// frame instead of shimmering. Input changes within `noise_tolerance` of the previous
+/// frame are treated as sensor noise and ignored, which makes every per-pixel algorithm
+/// (ordered, noise, halftone, threshold) stable on still parts of the picture. The
+/// error-diffusion algorithms additionally lean towards last frame's output by
+/// `stability` (0–1), fading out where the input moved. Use a fixed palette rather than
+/// one extracted per frame, and call `reset` after a cut or a settings change.
+#[wasm_bindgen]
+pub struct FrameDitherer {
+    stability: f32,
+    noise_tolerance: u32,
+    size: (u32, u32),
+    previous_input: Option<Vec<u8>>,
+    previous_output: Option<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl FrameDitherer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(stability: Option<f32>, noise_tolerance: Option<u32>) -> FrameDitherer {
+        FrameDitherer {
+            stability: stability.unwrap_or(DEFAULT_TEMPORAL_STABILITY).clamp(0.0, 1.0),
+            noise_tolerance: noise_tolerance.unwrap_or(DEFAULT_NOISE_TOLERANCE),
+            size: (0, 0),
+            previous_input: None,
+            previous_output: None,
+        }
+    }
+
+    /// Forgets the previous frame, so the next one is dithered from scratch.
+    pub fn reset(&mut self) {
+        self.previous_input = None;
+        self.previous_output = None;
+    }
+
+    /// Dithers the next frame in place. Takes the same arguments as `apply_dither`.
+    pub fn dither(&mut self, algorithm: &str, data: &mut [u8], width: u32, height: u32, options: &DitherOptions) {
+        if self.size != (width, height) {
+            self.reset();
+            self.size = (width, height);
+        }
+        let weights = self.stabilize_input(data);
+        self.previous_input = Some(data.to_vec());
+        let previous_frame = self
+            .previous_output
+            .take()
+            .zip(weights)
+            .map(|(output, weights)| PreviousFrame { output, weights });
+        dither_frame(algorithm, data, width, height, options, previous_frame);
+        self.previous_output = Some(data.to_vec());
+    }
+}
+
+impl FrameDitherer {
+    /// Snaps pixels that moved less than the noise tolerance back to the previous input and
+    /// returns each pixel's pull towards the previous output, or `None` on the first frame.
+    fn stabilize_input(&self, data: &mut [u8]) -> Option<Vec<f32>> {
+        let previous = self.previous_input.as_ref()?;
+        let tolerance = self.noise_tolerance as i32;
+        let stability = self.stability;
+        Some(
+            data.par_chunks_exact_mut(4)
+                .zip(previous.par_chunks_exact(4))
+                .map(|(pixel, before)| {
+                    let change = (0..4).map(|c| (pixel[c] as i32 - before[c] as i32).abs()).max().unwrap_or(0);
+                    if change <= tolerance {
+                        pixel.copy_from_slice(before);
+                        stability
+                    } else {
+                        stability * (1.0 - change as f32 / TEMPORAL_MOTION_RANGE).max(0.0)
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An opaque `w`×`h` RGBA image whose red, green and blue channels ramp across it.
+    fn gradient(w: usize, h: usize) -> Vec<u8> {
+        (0..w * h)
+            .flat_map(|i| {
+                let (x, y) = (i % w, i / w);
+                [(x * 255 / (w - 1)) as u8, (y * 255 / (h - 1)) as u8, ((x + y) * 255 / (w + h - 2)) as u8, 255]
+            })
+            .collect()
+    }
+
+    /// An opaque `w`×`h` RGBA image of a single gray `value`.
+    fn flat_gray(value: u8, w: usize, h: usize) -> Vec<u8> {
+        [value, value, value, 255].repeat(w * h)
+    }
+
+    /// Fraction of the pixels of a black-and-white result that are white.
+    fn white_fraction(data: &[u8]) -> f32 {
+        data.chunks_exact(4).filter(|pixel| pixel[0] == 255).count() as f32 / (data.len() / 4) as f32
+    }
+
+    #[test]
+    fn palette_output_stays_inside_the_palette() {
+        let palette = [0, 0, 0, 255, 0, 0, 40, 200, 255];
+        let options = DitherOptions { palette: Some(palette.to_vec()), ..DitherOptions::default() };
+        // CMYK halftoning always prints its own inks.
+        for &algorithm in ALGORITHM_REGISTRY.keys().filter(|&&name| name != "cmyk-halftone") {
+            let mut data = gradient(32, 16);
+            apply_dither(algorithm, &mut data, 32, 16, &options);
+            for pixel in data.chunks_exact(4) {
+                assert!(palette.chunks_exact(3).any(|color| color == &pixel[..3]), "{algorithm}: {pixel:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn palette_presets_are_used_unless_a_custom_palette_is_given() {
+        assert_eq!(get_palette_preset("gameboy").map(|bytes| bytes.len()), Some(4 * 3));
+        assert_eq!(get_palette_preset("zx-spectrum").map(|bytes| bytes.len()), Some(15 * 3));
+        assert_eq!(get_palette_preset("no-such-palette"), None);
+
+        let preset = DitherOptions { palette_preset: Some("pico-8".to_string()), ..DitherOptions::default() };
+        let resolved = preset.resolve_palette(&[]).map(|palette| palette.to_rgb_bytes());
+        assert_eq!(resolved, get_palette_preset("pico-8"));
+
+        let custom = [0, 0, 0, 255, 255, 255];
+        let both = DitherOptions { palette: Some(custom.to_vec()), ..preset };
+        assert_eq!(both.resolve_palette(&[]).map(|palette| palette.to_rgb_bytes()), Some(custom.to_vec()));
+    }
+
+    #[test]
+    fn extracted_palettes_follow_the_seed() {
+        let data = gradient(32, 16);
+        for seed in [1, 7] {
+            let options = DitherOptions {
+                palette_extract: Some("kmeans".to_string()),
+                palette_size: 6,
+                seed,
+                ..DitherOptions::default()
+            };
+            let resolved = options.resolve_palette(&data).map(|palette| palette.to_rgb_bytes());
+            let extracted = quantize::extract_palette("kmeans", &data, 6, seed).map(|palette| palette.to_rgb_bytes());
+            assert!(resolved.is_some());
+            assert_eq!(resolved, extracted, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn flat_gray_keeps_its_mean_tone() {
+        for linear_light in [false, true] {
+            let options = DitherOptions { bayer_width: 8, bayer_height: 8, linear_light, ..DitherOptions::default() };
+            for algorithm in [
+                "floyd-steinberg",
+                "false-floyd-steinberg",
+                "jarvis",
+                "stucki",
+                "burkes",
+                "sierra",
+                "sierra-two-row",
+                "sierra-lite",
+                "fan",
+                "shiau-fan",
+                "shiau-fan-2",
+                "bayer",
+                "bayer-recursive",
+                "blue-noise",
+                "halftone",
+                "line-screen",
+                "wavy-lines",
+                "concentric-circles",
+                "crosshatch",
+                "riemersma",
+                "ostromoukhov",
+                "zhou-fang",
+                "knoll",
+                "yliluoma",
+                "white-noise",
+                "triangular-noise",
+                "interleaved-gradient-noise",
+                "dot-diffusion",
+            ] {
+                for value in [32, 96, 160, 224] {
+                    let mut data = flat_gray(value, 64, 64);
+                    apply_dither(algorithm, &mut data, 64, 64, &options);
+                    // In linear light the share of white pixels matches the emitted light.
+                    let expected = if linear_light { srgb_byte_to_linear(value) } else { value as f32 / 255.0 };
+                    let fraction = white_fraction(&data);
+                    assert!((fraction - expected).abs() < 0.02, "{algorithm} {value} {linear_light}: {fraction}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multi_level_output_uses_only_the_requested_levels_and_keeps_the_tone() {
+        let options = DitherOptions { levels: 4, ..DitherOptions::default() };
+        for algorithm in ["floyd-steinberg", "jarvis", "sierra", "bayer", "triangular-noise"] {
+            for value in [40, 100, 200] {
+                let mut data = flat_gray(value, 64, 64);
+                apply_dither(algorithm, &mut data, 64, 64, &options);
+                assert!(data.chunks_exact(4).all(|pixel| [0, 85, 170, 255].contains(&pixel[0])), "{algorithm}");
+                let mean = data.chunks_exact(4).map(|pixel| pixel[0] as f32).sum::<f32>() / (64 * 64) as f32;
+                assert!((mean - value as f32).abs() < 3.0, "{algorithm} {value}: {mean}");
+            }
+        }
+    }
+
+    #[test]
+    fn serpentine_scanning_keeps_the_tone_but_changes_the_pattern() {
+        let raster = DitherOptions::default();
+        let serpentine = DitherOptions { serpentine: true, ..DitherOptions::default() };
+        for algorithm in ["floyd-steinberg", "jarvis", "sierra-lite"] {
+            let mut expected = flat_gray(100, 64, 64);
+            apply_dither(algorithm, &mut expected, 64, 64, &raster);
+            let mut data = flat_gray(100, 64, 64);
+            apply_dither(algorithm, &mut data, 64, 64, &serpentine);
+            assert_ne!(data, expected, "{algorithm}");
+            // The first row is scanned left to right either way.
+            assert_eq!(data[..64 * 4], expected[..64 * 4], "{algorithm}");
+            let fraction = white_fraction(&data);
+            assert!((fraction - 100.0 / 255.0).abs() < 0.02, "{algorithm}: {fraction}");
+        }
+    }
+
+    #[test]
+    fn parse_custom_kernel_normalizes_weights() {
+        let kernel = parse_custom_kernel(&[1.0, 0.0, 7.0, -1.0, 1.0, 3.0, 0.0, 1.0, 5.0, 1.0, 1.0, 1.0], 0.0).unwrap();
+        assert_eq!(kernel, vec![(1, 0, 7.0 / 16.0), (-1, 1, 3.0 / 16.0), (0, 1, 5.0 / 16.0), (1, 1, 1.0 / 16.0)]);
+        let kernel = parse_custom_kernel(&[1.0, 0.0, 1.0, 0.0, 1.0, 1.0], 8.0).unwrap();
+        assert_eq!(kernel, vec![(1, 0, 0.125), (0, 1, 0.125)]);
+    }
+
+    #[test]
+    fn parse_custom_kernel_rejects_backward_offsets() {
+        for (dx, dy) in [(0.0, 0.0), (-1.0, 0.0), (0.0, -1.0), (2.0, -1.0)] {
+            assert!(parse_custom_kernel(&[dx, dy, 1.0], 1.0).is_err(), "({dx}, {dy})");
+        }
+    }
+
+    #[test]
+    fn parse_custom_kernel_rejects_fractional_offsets() {
+        assert!(parse_custom_kernel(&[1.5, 0.0, 1.0], 1.0).is_err());
+        assert!(parse_custom_kernel(&[0.0, 0.5, 1.0], 1.0).is_err());
+    }
+
+    #[test]
+    fn parse_custom_kernel_rejects_zero_divisor() {
+        // Weights summing to 0 leave nothing to divide by when the divisor is derived.
+        assert!(parse_custom_kernel(&[1.0, 0.0, 1.0, 0.0, 1.0, -1.0], 0.0).is_err());
+        assert!(parse_custom_kernel(&[1.0, 0.0, 1.0], f32::NAN).is_err());
+    }
+
+    #[test]
+    fn recursive_bayer_matrix_is_the_classic_one() {
+        assert_eq!(generate_recursive_bayer_matrix(2), vec![vec![0, 2], vec![3, 1]]);
+        assert_eq!(
+            generate_recursive_bayer_matrix(4),
+            vec![vec![0, 8, 2, 10], vec![12, 4, 14, 6], vec![3, 11, 1, 9], vec![15, 7, 13, 5]]
+        );
+    }
+
+    #[test]
+    fn blue_noise_seeds_rearrange_one_cached_texture() {
+        let sorted = |map: &ThresholdMap| {
+            let mut values = map.values.clone();
+            values.sort_by(f32::total_cmp);
+            values
+        };
+        let first = blue_noise_map(32, 1);
+        let second = blue_noise_map(32, 2);
+        assert_ne!(first.values, second.values);
+        assert_eq!(sorted(&first), sorted(&second));
+        assert!(BLUE_NOISE_MAPS.lock().unwrap().keys().all(|&size| (16..=128).contains(&size)));
+    }
+
+    #[test]
+    fn registered_rank_matrix_dithers_like_the_built_in_one() {
+        let ranks: Vec<u8> = generate_recursive_bayer_matrix(4).into_iter().flatten().collect();
+        assert!(register_threshold_map_u8("test-bayer-4", &ranks, 4, 4).is_ok());
+        let options = DitherOptions::default();
+        let mut custom = gradient(48, 32);
+        let mut built_in = custom.clone();
+        apply_dither("test-bayer-4", &mut custom, 48, 32, &options);
+        apply_dither("bayer-recursive", &mut built_in, 48, 32, &options);
+        assert_eq!(custom, built_in);
+    }
+
+    #[test]
+    fn image_threshold_maps_read_the_red_channel() {
+        let ranks: Vec<u8> = generate_recursive_bayer_matrix(4).into_iter().flatten().collect();
+        // Green and blue run against the ranks; only red should matter.
+        let rgba: Vec<u8> = ranks.iter().flat_map(|&r| [r, 15 - r, 255 - r, 0]).collect();
+        assert!(register_threshold_map_image("test-bayer-4-image", &rgba, 4, 4).is_ok());
+        let options = DitherOptions::default();
+        let mut custom = gradient(48, 32);
+        let mut built_in = custom.clone();
+        apply_dither("test-bayer-4-image", &mut custom, 48, 32, &options);
+        apply_dither("bayer-recursive", &mut built_in, 48, 32, &options);
+        assert_eq!(custom, built_in);
+    }
+
+    #[test]
+    fn cmyk_plates_print_gray_with_black_ink_only() {
+        let plates = cmyk_plates(&flat_gray(128, 32, 32), 32, 32, &DitherOptions::default());
+        let plane = 32 * 32;
+        assert!(plates[..3 * plane].iter().all(|&ink| ink == 0));
+        let black = plates[3 * plane..].iter().filter(|&&ink| ink == 1).count() as f32 / plane as f32;
+        assert!((black - 0.5).abs() < 0.05, "{black}");
+    }
+
+    #[test]
+    fn hilbert_order_visits_every_pixel_once() {
+        for (w, h) in [(16, 16), (13, 5), (1, 9), (0, 4), (1000, 3)] {
+            let mut order: Vec<usize> = hilbert_order(w, h).collect();
+            order.sort_unstable();
+            assert_eq!(order, (0..w * h).collect::<Vec<_>>(), "{w}x{h}");
+        }
+        // On a full square every step moves to a neighboring pixel.
+        let order: Vec<usize> = hilbert_order(16, 16).collect();
+        for pair in order.windows(2) {
+            let (a, b) = ((pair[0] % 16, pair[0] / 16), (pair[1] % 16, pair[1] / 16));
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+        }
+    }
+
+    #[test]
+    fn ostromoukhov_coefficients_sum_to_one_and_mirror_around_mid_gray() {
+        for level in 0..256 {
+            let weights = OSTROMOUKHOV_COEFFICIENTS[level];
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-5, "level {level}");
+            assert_eq!(weights, OSTROMOUKHOV_COEFFICIENTS[255 - level]);
+        }
+    }
+
+    #[test]
+    fn every_knuth_class_occurs_once_per_tile() {
+        let positions = class_positions();
+        for (class, &(y, x)) in positions.iter().enumerate() {
+            assert_eq!(KNUTH_CLASS_MATRIX[y][x] as usize, class);
+        }
+    }
+
+    #[test]
+    fn edge_enhancement_keeps_a_step_edge_sharp() {
+        // Dark gray on the left, light gray on the right: a contrast of 0.5 in the source.
+        let (w, h) = (64, 64);
+        let step: Vec<u8> =
+            (0..w * h).flat_map(|i| if i % w < w / 2 { [64, 64, 64, 255] } else { [192, 192, 192, 255] }).collect();
+        let edge_contrast = |algorithm: &str, gain: f32| {
+            let options = DitherOptions { edge_enhancement: Some(gain), ..DitherOptions::default() };
+            let mut data = step.clone();
+            apply_dither(algorithm, &mut data, w as u32, h as u32, &options);
+            let column_white = |x: usize| (0..h).filter(|&y| data[(y * w + x) * 4] == 255).count() as f32 / h as f32;
+            column_white(w / 2) - column_white(w / 2 - 1)
+        };
+        for algorithm in ["floyd-steinberg", "jarvis", "ostromoukhov"] {
+            let sharpened = edge_contrast(algorithm, 2.0);
+            assert!(sharpened > 0.9 && sharpened >= edge_contrast(algorithm, 0.0), "{algorithm}: {sharpened}");
+        }
+    }
+
+    #[test]
+    fn edge_enhancement_keeps_the_tone_of_flat_areas() {
+        let options = DitherOptions { edge_enhancement: Some(2.0), ..DitherOptions::default() };
+        for algorithm in ["floyd-steinberg", "jarvis", "riemersma", "ostromoukhov"] {
+            for value in [64, 192] {
+                let mut data = flat_gray(value, 128, 128);
+                apply_dither(algorithm, &mut data, 128, 128, &options);
+                let expected = value as f32 / 255.0;
+                assert!((white_fraction(&data) - expected).abs() < 0.02, "{algorithm} at {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn frame_ditherer_output_is_unchanged_under_small_noise() {
+        let options = DitherOptions::default();
+        for algorithm in ["floyd-steinberg", "bayer", "white-noise"] {
+            let mut ditherer = FrameDitherer::new(None, None);
+            let mut first = gradient(48, 32);
+            ditherer.dither(algorithm, &mut first, 48, 32, &options);
+            // Sensor noise of up to ±2 per channel, well inside the default tolerance.
+            let mut second = gradient(48, 32);
+            for (i, value) in second.iter_mut().enumerate() {
+                if i % 4 != 3 {
+                    *value = value.saturating_add_signed((i % 5) as i8 - 2);
+                }
+            }
+            ditherer.dither(algorithm, &mut second, 48, 32, &options);
+            assert_eq!(first, second, "{algorithm}");
+        }
+    }
+
+    #[test]
+    fn frame_ditherer_starts_over_when_the_size_changes() {
+        let options = DitherOptions::default();
+        let mut ditherer = FrameDitherer::new(None, None);
+        let mut first = gradient(48, 32);
+        ditherer.dither("floyd-steinberg", &mut first, 48, 32, &options);
+        let mut frame = gradient(32, 48);
+        let mut still = frame.clone();
+        ditherer.dither("floyd-steinberg", &mut frame, 32, 48, &options);
+        apply_dither("floyd-steinberg", &mut still, 32, 48, &options);
+        assert_eq!(frame, still);
+    }
+}
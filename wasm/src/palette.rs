@@ -0,0 +1,239 @@
+// palette.rs
+//
+// Color palettes used to quantize dithered output to an arbitrary set of RGB colors.
+
+use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use crate::color::{srgb_to_linear, ColorMetric};
+
+/// A set of RGB colors that dithered pixels are snapped to, together with the metric used
+/// to decide which color is closest.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    colors: Vec<[f32; 3]>,
+    metric: ColorMetric,
+    /// Whether the RGB metrics compare (and diffuse) linear-light values.
+    linear_light: bool,
+    /// `colors` converted into the metric's working space.
+    space_colors: Vec<[f32; 3]>,
+}
+
+impl Palette {
+    fn new(colors: Vec<[f32; 3]>) -> Palette {
+        Palette {
+            space_colors: colors.clone(),
+            colors,
+            metric: ColorMetric::Rgb,
+            linear_light: false,
+        }
+    }
+
+    /// Builds a palette from packed RGB triplets (`[r0, g0, b0, r1, g1, b1, ...]`).
+    /// Trailing bytes that do not form a full triplet are ignored.
+    /// Returns `None` if the buffer does not contain at least one color.
+    pub fn from_rgb_bytes(bytes: &[u8]) -> Option<Palette> {
+        let colors: Vec<[f32; 3]> = bytes
+            .chunks_exact(3)
+            .map(|c| [c[0] as f32, c[1] as f32, c[2] as f32])
+            .collect();
+        if colors.is_empty() {
+            None
+        } else {
+            Some(Palette::new(colors))
+        }
+    }
+
+    /// Builds a palette from `0xRRGGBB` values.
+    pub fn from_hex(colors: &[u32]) -> Palette {
+        Palette::new(
+            colors
+                .iter()
+                .map(|&c| [((c >> 16) & 0xFF) as f32, ((c >> 8) & 0xFF) as f32, (c & 0xFF) as f32])
+                .collect(),
+        )
+    }
+
+    /// Returns the palette matched with `metric` instead of plain RGB distance.
+    pub fn with_metric(mut self, metric: ColorMetric) -> Palette {
+        self.metric = metric;
+        self.refresh_space_colors();
+        self
+    }
+
+    /// Returns the palette with linear-light matching switched on or off. This only affects
+    /// the RGB metrics; CIELAB and OKLab already decode sRGB before comparing.
+    pub fn with_linear_light(mut self, linear_light: bool) -> Palette {
+        self.linear_light = linear_light;
+        self.refresh_space_colors();
+        self
+    }
+
+    fn refresh_space_colors(&mut self) {
+        self.space_colors = self.colors.iter().map(|&c| self.to_space(c)).collect();
+    }
+
+    /// Packs the palette back into RGB triplets.
+    pub fn to_rgb_bytes(&self) -> Vec<u8> {
+        self.colors
+            .iter()
+            .flat_map(|c| [c[0] as u8, c[1] as u8, c[2] as u8])
+            .collect()
+    }
+
+    pub fn colors(&self) -> &[[f32; 3]] {
+        &self.colors
+    }
+
+    pub fn metric(&self) -> ColorMetric {
+        self.metric
+    }
+
+    pub fn linear_light(&self) -> bool {
+        self.linear_light
+    }
+
+    /// Converts an sRGB color into the working space of this palette's metric.
+    #[inline(always)]
+    pub fn to_space(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match self.metric {
+            ColorMetric::Rgb | ColorMetric::WeightedRgb if self.linear_light => [
+                255.0 * srgb_to_linear(rgb[0]),
+                255.0 * srgb_to_linear(rgb[1]),
+                255.0 * srgb_to_linear(rgb[2]),
+            ],
+            metric => metric.to_space(rgb),
+        }
+    }
+
+    /// The color at `index` in the metric's working space.
+    #[inline(always)]
+    pub fn space_color(&self, index: usize) -> [f32; 3] {
+        self.space_colors[index]
+    }
+
+    /// The color at `index` as sRGB.
+    #[inline(always)]
+    pub fn color(&self, index: usize) -> [f32; 3] {
+        self.colors[index]
+    }
+
+    /// Returns the index of the palette color closest to `color`, which must already be in
+    /// the metric's working space (see [`Palette::to_space`]).
+    #[inline(always)]
+    pub fn nearest_index(&self, color: [f32; 3]) -> usize {
+        let mut best = 0;
+        let mut best_dist = f32::MAX;
+        for (i, &candidate) in self.space_colors.iter().enumerate() {
+            let dist = self.metric.distance(color, candidate);
+            if dist < best_dist {
+                best_dist = dist;
+                best = i;
+            }
+        }
+        best
+    }
+
+    /// Returns the palette color closest to the sRGB `color`.
+    #[inline(always)]
+    pub fn nearest(&self, color: [f32; 3]) -> [f32; 3] {
+        self.colors[self.nearest_index(self.to_space(color))]
+    }
+
+    /// Amplitude used to spread ordered-dither thresholds across the palette: the average
+    /// per-channel (Chebyshev) gap between each color and its nearest neighbor.
+    pub fn spread(&self) -> f32 {
+        if self.colors.len() < 2 {
+            return 255.0;
+        }
+        let total: f32 = self
+            .colors
+            .iter()
+            .map(|a| {
+                // Duplicate entries are skipped so they don't collapse the spread to zero.
+                let gap = self
+                    .colors
+                    .iter()
+                    .map(|b| (0..3).map(|c| (a[c] - b[c]).abs()).fold(0.0, f32::max))
+                    .filter(|&d| d > 0.0)
+                    .fold(f32::MAX, f32::min);
+                if gap == f32::MAX { 255.0 } else { gap }
+            })
+            .sum();
+        total / self.colors.len() as f32
+    }
+}
+
+// === Retro Hardware Presets ===
+
+// Game Boy (DMG-01): 4 shades of green
+const GAMEBOY_DMG: &[u32] = &[0x0F380F, 0x306230, 0x8BAC0F, 0x9BBC0F];
+
+// CGA mode 4, palette 0 (green/red/brown), low and high intensity
+const CGA_MODE4_PAL0_LOW: &[u32] = &[0x000000, 0x00AA00, 0xAA0000, 0xAA5500];
+const CGA_MODE4_PAL0_HIGH: &[u32] = &[0x000000, 0x55FF55, 0xFF5555, 0xFFFF55];
+
+// CGA mode 4, palette 1 (cyan/magenta/white), low and high intensity
+const CGA_MODE4_PAL1_LOW: &[u32] = &[0x000000, 0x00AAAA, 0xAA00AA, 0xAAAAAA];
+const CGA_MODE4_PAL1_HIGH: &[u32] = &[0x000000, 0x55FFFF, 0xFF55FF, 0xFFFFFF];
+
+// CGA mode 5 (cyan/red/white), low and high intensity
+const CGA_MODE5_LOW: &[u32] = &[0x000000, 0x00AAAA, 0xAA0000, 0xAAAAAA];
+const CGA_MODE5_HIGH: &[u32] = &[0x000000, 0x55FFFF, 0xFF5555, 0xFFFFFF];
+
+// EGA default 16-color palette (also the full CGA text-mode palette)
+const EGA_16: &[u32] = &[
+    0x000000, 0x0000AA, 0x00AA00, 0x00AAAA, 0xAA0000, 0xAA00AA, 0xAA5500, 0xAAAAAA,
+    0x555555, 0x5555FF, 0x55FF55, 0x55FFFF, 0xFF5555, 0xFF55FF, 0xFFFF55, 0xFFFFFF,
+];
+
+// Commodore 64 (Pepto's measured VIC-II colors)
+const COMMODORE_64: &[u32] = &[
+    0x000000, 0xFFFFFF, 0x68372B, 0x70A4B2, 0x6F3D86, 0x588D43, 0x352879, 0xB8C76F,
+    0x6F4F25, 0x433900, 0x9A6759, 0x444444, 0x6C6C6C, 0x9AD284, 0x6C5EB5, 0x959595,
+];
+
+// ZX Spectrum: 8 normal colors plus the BRIGHT attribute set (bright black is black)
+const ZX_SPECTRUM: &[u32] = &[
+    0x000000, 0x0000D7, 0xD70000, 0xD700D7, 0x00D700, 0x00D7D7, 0xD7D700, 0xD7D7D7,
+    0x0000FF, 0xFF0000, 0xFF00FF, 0x00FF00, 0x00FFFF, 0xFFFF00, 0xFFFFFF,
+];
+
+// PICO-8 fantasy console
+const PICO_8: &[u32] = &[
+    0x000000, 0x1D2B53, 0x7E2553, 0x008751, 0xAB5236, 0x5F574F, 0xC2C3C7, 0xFFF1E8,
+    0xFF004D, 0xFFA300, 0xFFEC27, 0x00E436, 0x29ADFF, 0x83769C, 0xFF77A8, 0xFFCCAA,
+];
+
+// NES (2C02 PPU), duplicate blacks removed
+const NES: &[u32] = &[
+    0x7C7C7C, 0x0000FC, 0x0000BC, 0x4428BC, 0x940084, 0xA80020, 0xA81000, 0x881400,
+    0x503000, 0x007800, 0x006800, 0x005800, 0x004058, 0x000000,
+    0xBCBCBC, 0x0078F8, 0x0058F8, 0x6844FC, 0xD800CC, 0xE40058, 0xF83800, 0xE45C10,
+    0xAC7C00, 0x00B800, 0x00A800, 0x00A844, 0x008888,
+    0xF8F8F8, 0x3CBCFC, 0x6888FC, 0x9878F8, 0xF878F8, 0xF85898, 0xF87858, 0xFCA044,
+    0xF8B800, 0xB8F818, 0x58D854, 0x58F898, 0x00E8D8, 0x787878,
+    0xFCFCFC, 0xA4E4FC, 0xB8B8F8, 0xD8B8F8, 0xF8B8F8, 0xF8A4C0, 0xF0D0B0, 0xFCE0A8,
+    0xF8D878, 0xD8F878, 0xB8F8B8, 0xB8F8D8, 0x00FCFC, 0xF8D8F8,
+];
+
+// Apple II hi-res graphics (NTSC artifact colors)
+const APPLE_II_HIRES: &[u32] = &[0x000000, 0xFFFFFF, 0x14F53C, 0xFF44FD, 0xFF6A3C, 0x14CFFD];
+
+/// Named palette presets, looked up by the string passed to `apply_dither`.
+pub static PALETTE_REGISTRY: Lazy<HashMap<&'static str, Palette>> = Lazy::new(|| {
+    let mut m: HashMap<&'static str, Palette> = HashMap::new();
+    m.insert("gameboy", Palette::from_hex(GAMEBOY_DMG));
+    m.insert("cga-mode4-pal0-low", Palette::from_hex(CGA_MODE4_PAL0_LOW));
+    m.insert("cga-mode4-pal0-high", Palette::from_hex(CGA_MODE4_PAL0_HIGH));
+    m.insert("cga-mode4-pal1-low", Palette::from_hex(CGA_MODE4_PAL1_LOW));
+    m.insert("cga-mode4-pal1-high", Palette::from_hex(CGA_MODE4_PAL1_HIGH));
+    m.insert("cga-mode5-low", Palette::from_hex(CGA_MODE5_LOW));
+    m.insert("cga-mode5-high", Palette::from_hex(CGA_MODE5_HIGH));
+    m.insert("ega", Palette::from_hex(EGA_16));
+    m.insert("c64", Palette::from_hex(COMMODORE_64));
+    m.insert("zx-spectrum", Palette::from_hex(ZX_SPECTRUM));
+    m.insert("pico-8", Palette::from_hex(PICO_8));
+    m.insert("nes", Palette::from_hex(NES));
+    m.insert("apple2-hires", Palette::from_hex(APPLE_II_HIRES));
+    m
+});
@@ -0,0 +1,107 @@
+// pattern.rs
+//
+// Pattern dithering against arbitrary palettes. Each pixel gets a mixing plan — which palette
+// colors to blend, and in what proportion, to reproduce its color — and the threshold
+// matrix then decides which color of the plan that pixel shows. All mixing happens in the
+// working space of the palette's color metric.
+
+use crate::color::LumaModel;
+use crate::palette::Palette;
+
+/// Brightness used to order plan entries, so that the threshold matrix spreads dark and
+/// light colors the same way it spreads gray levels.
+fn brightness(palette: &Palette, index: usize, luma: LumaModel) -> f32 {
+    let [r, g, b] = palette.color(index);
+    luma.apply(r, g, b)
+}
+
+/// Thomas Knoll's pattern dithering: fills `plan` with palette indices by repeatedly
+/// snapping `target` plus the error accumulated so far, then sorts it by `luma`. The
+/// plan averages to `target`; a pixel whose threshold is `t` shows `plan[t * plan.len()]`.
+pub fn knoll_plan(palette: &Palette, target: [f32; 3], luma: LumaModel, plan: &mut [usize]) {
+    let mut error = [0.0f32; 3];
+    for slot in plan.iter_mut() {
+        let attempt = [target[0] + error[0], target[1] + error[1], target[2] + error[2]];
+        let index = palette.nearest_index(attempt);
+        let chosen = palette.space_color(index);
+        for c in 0..3 {
+            error[c] += target[c] - chosen[c];
+        }
+        *slot = index;
+    }
+    plan.sort_by(|&a, &b| brightness(palette, a, luma).total_cmp(&brightness(palette, b, luma)));
+}
+
+/// Yliluoma's ordered dithering algorithm 1: the pair of palette colors and mixing ratio
+/// whose blend comes closest to `target`. The ratio is quantized to `steps` (the number of
+/// threshold levels), and mixing very different colors is penalized so that, among
+/// equally good blends, the lower-contrast pattern wins. Returns `(first, second, ratio)`;
+/// pixels whose threshold is below `ratio` show `second`, the others `first`.
+pub fn yliluoma_mix(palette: &Palette, target: [f32; 3], steps: usize) -> (usize, usize, f32) {
+    let metric = palette.metric();
+    let steps = steps.max(1) as f32;
+    let count = palette.colors().len();
+    let mut best = (0, 0, 0.0);
+    let mut best_penalty = f32::MAX;
+    for i in 0..count {
+        let a = palette.space_color(i);
+        for j in i..count {
+            let b = palette.space_color(j);
+            let d = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let length_sq = d[0] * d[0] + d[1] * d[1] + d[2] * d[2];
+            // Project the target onto the segment between the two colors.
+            let ratio = if length_sq > 0.0 {
+                let t = ((target[0] - a[0]) * d[0] + (target[1] - a[1]) * d[1] + (target[2] - a[2]) * d[2])
+                    / length_sq;
+                (t.clamp(0.0, 1.0) * steps).round() / steps
+            } else {
+                0.0
+            };
+            let mix = [a[0] + d[0] * ratio, a[1] + d[1] * ratio, a[2] + d[2] * ratio];
+            // Yliluoma weighs the pair's squared contrast by 0.1; dividing that by the squared
+            // ratio resolution keeps it on the order of the mixing error the matrix cannot
+            // avoid, so it settles near-ties without pulling sparse palettes off the target
+            // tone. Both terms are squared distances, whatever the metric.
+            let contrast = metric.squared_distance(a, b) * 0.1 * ((ratio - 0.5).abs() + 0.5) / (steps * steps);
+            let penalty = metric.squared_distance(target, mix) + contrast;
+            if penalty < best_penalty {
+                best_penalty = penalty;
+                best = (i, j, ratio);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorMetric;
+
+    #[test]
+    fn knoll_plan_averages_to_the_target() {
+        let palette = Palette::from_hex(&[0x000000, 0xff0000, 0x00ff00, 0xffffff]);
+        let target = palette.to_space([120.0, 90.0, 40.0]);
+        let mut plan = [0; 64];
+        knoll_plan(&palette, target, LumaModel::Rec709, &mut plan);
+        for (c, &value) in target.iter().enumerate() {
+            let mean = plan.iter().map(|&i| palette.space_color(i)[c]).sum::<f32>() / plan.len() as f32;
+            assert!((mean - value).abs() < 8.0, "channel {c}: {mean} vs {value}");
+        }
+        let luma = |i| brightness(&palette, i, LumaModel::Rec709);
+        assert!(plan.windows(2).all(|pair| luma(pair[0]) <= luma(pair[1])));
+    }
+
+    #[test]
+    fn yliluoma_mixes_black_and_white_for_gray() {
+        let palette = Palette::from_hex(&[0x000000, 0xffffff, 0xff0000]);
+        // Mixing happens in the metric's working space; RGB and both CIELAB metrics put
+        // 64 gray a quarter of the way from black to white.
+        for metric in [ColorMetric::Rgb, ColorMetric::Cie76, ColorMetric::Ciede2000] {
+            let palette = palette.clone().with_metric(metric);
+            let (first, second, ratio) = yliluoma_mix(&palette, palette.to_space([64.0, 64.0, 64.0]), 16);
+            assert_eq!((first, second), (0, 1), "{metric:?}");
+            assert!((ratio - 0.25).abs() < 0.01, "{metric:?}: {ratio}");
+        }
+    }
+}
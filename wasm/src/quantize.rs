@@ -0,0 +1,316 @@
+// quantize.rs
+//
+// Automatic palette extraction: reduces an RGBA image to an N-color palette.
+
+use rayon::prelude::*;
+use crate::palette::Palette;
+use crate::rng::XorShift32;
+
+/// Collects the RGB values of all visible pixels in an RGBA buffer.
+fn collect_pixels(data: &[u8]) -> Vec<[f32; 3]> {
+    data.chunks_exact(4)
+        .filter(|c| c[3] > 0)
+        .map(|c| [c[0] as f32, c[1] as f32, c[2] as f32])
+        .collect()
+}
+
+/// Extracts a palette of at most `colors` entries from an RGBA buffer.
+/// `method` is one of `"median-cut"`, `"kmeans"` or `"octree"`; unknown names fall back to
+/// median cut. Returns `None` for an empty (or fully transparent) image.
+pub fn extract_palette(method: &str, data: &[u8], colors: usize, seed: u32) -> Option<Palette> {
+    let pixels = collect_pixels(data);
+    if pixels.is_empty() || colors == 0 {
+        return None;
+    }
+    let result = match method {
+        "kmeans" => kmeans(&pixels, colors, seed),
+        "octree" => octree(&pixels, colors),
+        _ => median_cut(&pixels, colors),
+    };
+    let bytes: Vec<u8> = result
+        .iter()
+        .flat_map(|c| c.map(|v| v.round().clamp(0.0, 255.0) as u8))
+        .collect();
+    Palette::from_rgb_bytes(&bytes)
+}
+
+#[inline(always)]
+fn dist_sq(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let dr = a[0] - b[0];
+    let dg = a[1] - b[1];
+    let db = a[2] - b[2];
+    dr * dr + dg * dg + db * db
+}
+
+fn mean(pixels: &[[f32; 3]]) -> [f32; 3] {
+    let mut sum = [0.0f64; 3];
+    for p in pixels {
+        sum[0] += p[0] as f64;
+        sum[1] += p[1] as f64;
+        sum[2] += p[2] as f64;
+    }
+    let n = pixels.len().max(1) as f64;
+    [(sum[0] / n) as f32, (sum[1] / n) as f32, (sum[2] / n) as f32]
+}
+
+// === Median Cut ===
+
+/// Returns the channel with the widest range in `pixels` and that range.
+fn widest_channel(pixels: &[[f32; 3]]) -> (usize, f32) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in pixels {
+        for c in 0..3 {
+            min[c] = min[c].min(p[c]);
+            max[c] = max[c].max(p[c]);
+        }
+    }
+    (0..3)
+        .map(|c| (c, max[c] - min[c]))
+        .fold((0, -1.0), |best, cur| if cur.1 > best.1 { cur } else { best })
+}
+
+/// Heckbert's median cut: repeatedly splits the box with the widest channel range at its median.
+fn median_cut(pixels: &[[f32; 3]], colors: usize) -> Vec<[f32; 3]> {
+    let mut boxes: Vec<Vec<[f32; 3]>> = vec![pixels.to_vec()];
+    while boxes.len() < colors {
+        // Pick the box whose widest channel spans the largest range.
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range)
+            })
+            .filter(|&(_, _, range)| range > 0.0)
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+        let mut b = boxes.swap_remove(index);
+        b.sort_unstable_by(|p, q| p[channel].total_cmp(&q[channel]));
+        let upper = b.split_off(b.len() / 2);
+        boxes.push(b);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|b| mean(b)).collect()
+}
+
+// === K-Means ===
+
+const KMEANS_MAX_SAMPLES: usize = 65536;
+const KMEANS_MAX_ITERATIONS: usize = 24;
+
+/// Lloyd's k-means with k-means++ seeding on an evenly strided sample of the pixels.
+fn kmeans(pixels: &[[f32; 3]], colors: usize, seed: u32) -> Vec<[f32; 3]> {
+    let stride = pixels.len().div_ceil(KMEANS_MAX_SAMPLES).max(1);
+    let samples: Vec<[f32; 3]> = pixels.iter().step_by(stride).copied().collect();
+    let mut rng = XorShift32::new(seed);
+
+    // k-means++ initialization.
+    let mut centroids = vec![samples[(rng.next_u32() as usize) % samples.len()]];
+    let mut nearest_dist: Vec<f32> = samples.iter().map(|&p| dist_sq(p, centroids[0])).collect();
+    while centroids.len() < colors {
+        let total: f32 = nearest_dist.iter().sum();
+        if total <= 0.0 {
+            break;
+        }
+        let mut target = rng.next_f32() * total;
+        let mut chosen = samples.len() - 1;
+        for (i, &d) in nearest_dist.iter().enumerate() {
+            if target < d {
+                chosen = i;
+                break;
+            }
+            target -= d;
+        }
+        let centroid = samples[chosen];
+        centroids.push(centroid);
+        nearest_dist
+            .par_iter_mut()
+            .zip(samples.par_iter())
+            .for_each(|(d, &p)| *d = d.min(dist_sq(p, centroid)));
+    }
+
+    // Lloyd iterations.
+    let mut assignment = vec![0usize; samples.len()];
+    for _ in 0..KMEANS_MAX_ITERATIONS {
+        let changed: usize = assignment
+            .par_iter_mut()
+            .zip(samples.par_iter())
+            .map(|(a, &p)| {
+                let mut best = 0;
+                let mut best_dist = f32::MAX;
+                for (i, &c) in centroids.iter().enumerate() {
+                    let d = dist_sq(p, c);
+                    if d < best_dist {
+                        best_dist = d;
+                        best = i;
+                    }
+                }
+                let changed = (*a != best) as usize;
+                *a = best;
+                changed
+            })
+            .sum();
+        let mut sums = vec![[0.0f64; 3]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+        for (&a, p) in assignment.iter().zip(&samples) {
+            sums[a][0] += p[0] as f64;
+            sums[a][1] += p[1] as f64;
+            sums[a][2] += p[2] as f64;
+            counts[a] += 1;
+        }
+        for (i, centroid) in centroids.iter_mut().enumerate() {
+            // Empty clusters keep their previous centroid.
+            if counts[i] > 0 {
+                let n = counts[i] as f64;
+                *centroid = [(sums[i][0] / n) as f32, (sums[i][1] / n) as f32, (sums[i][2] / n) as f32];
+            }
+        }
+        if changed == 0 {
+            break;
+        }
+    }
+    centroids
+}
+
+// === Octree ===
+
+// Depth 6 keeps the tree bounded (64 levels per channel) while staying visually lossless
+// for palettes of up to a few hundred colors.
+const OCTREE_DEPTH: usize = 6;
+
+#[derive(Default)]
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    sum: [f64; 3],
+    count: usize,
+    is_leaf: bool,
+}
+
+/// Gervautz–Purgathofer octree quantization: build the color tree, then fold the least
+/// populated deepest nodes into their parents until at most `colors` leaves remain.
+fn octree(pixels: &[[f32; 3]], colors: usize) -> Vec<[f32; 3]> {
+    let mut nodes: Vec<OctreeNode> = vec![OctreeNode::default()];
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); OCTREE_DEPTH];
+    let mut leaf_count = 0usize;
+
+    for p in pixels {
+        let rgb = [p[0] as u8, p[1] as u8, p[2] as u8];
+        let mut node = 0;
+        for depth in 0..OCTREE_DEPTH {
+            let shift = 7 - depth;
+            let child = (((rgb[0] >> shift) & 1) << 2 | ((rgb[1] >> shift) & 1) << 1 | ((rgb[2] >> shift) & 1)) as usize;
+            node = match nodes[node].children[child] {
+                Some(next) => next,
+                None => {
+                    let next = nodes.len();
+                    nodes.push(OctreeNode::default());
+                    nodes[node].children[child] = Some(next);
+                    if depth + 1 < OCTREE_DEPTH {
+                        levels[depth + 1].push(next);
+                    } else {
+                        nodes[next].is_leaf = true;
+                        leaf_count += 1;
+                    }
+                    next
+                }
+            };
+        }
+        let leaf = &mut nodes[node];
+        leaf.sum[0] += p[0] as f64;
+        leaf.sum[1] += p[1] as f64;
+        leaf.sum[2] += p[2] as f64;
+        leaf.count += 1;
+    }
+
+    // Propagate pixel counts upward so reductions can pick the least populated node.
+    fn total(nodes: &mut [OctreeNode], index: usize) -> ([f64; 3], usize) {
+        if nodes[index].is_leaf {
+            return (nodes[index].sum, nodes[index].count);
+        }
+        let mut sum = [0.0; 3];
+        let mut count = 0;
+        for child in nodes[index].children.into_iter().flatten() {
+            let (s, c) = total(nodes, child);
+            sum[0] += s[0];
+            sum[1] += s[1];
+            sum[2] += s[2];
+            count += c;
+        }
+        nodes[index].sum = sum;
+        nodes[index].count = count;
+        (sum, count)
+    }
+    total(&mut nodes, 0);
+
+    for depth in (1..OCTREE_DEPTH).rev() {
+        levels[depth].sort_by_key(|&i| std::cmp::Reverse(nodes[i].count));
+        while leaf_count > colors {
+            let Some(index) = levels[depth].pop() else { break };
+            let children = nodes[index].children.iter().flatten().count();
+            nodes[index].children = [None; 8];
+            nodes[index].is_leaf = true;
+            leaf_count -= children - 1;
+        }
+    }
+
+    let mut leaves: Vec<([f64; 3], usize)> = Vec::with_capacity(leaf_count);
+    let mut stack = vec![0usize];
+    while let Some(index) = stack.pop() {
+        let node = &nodes[index];
+        if node.is_leaf {
+            leaves.push((node.sum, node.count));
+        } else {
+            stack.extend(node.children.iter().flatten());
+        }
+    }
+
+    let average = |(sum, count): ([f64; 3], usize)| {
+        let n = count.max(1) as f64;
+        [(sum[0] / n) as f32, (sum[1] / n) as f32, (sum[2] / n) as f32]
+    };
+
+    // Fewer colors than first-level octants: merge the closest pair of leaves until it fits.
+    while leaves.len() > colors {
+        let mut best = (0, 1, f32::MAX);
+        for i in 0..leaves.len() {
+            for j in i + 1..leaves.len() {
+                let d = dist_sq(average(leaves[i]), average(leaves[j]));
+                if d < best.2 {
+                    best = (i, j, d);
+                }
+            }
+        }
+        let (other_sum, other_count) = leaves.swap_remove(best.1);
+        let (sum, count) = &mut leaves[best.0];
+        sum[0] += other_sum[0];
+        sum[1] += other_sum[1];
+        sum[2] += other_sum[2];
+        *count += other_count;
+    }
+
+    leaves.into_iter().map(average).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_method_recovers_the_colors_of_a_four_color_image() {
+        let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]];
+        let data: Vec<u8> = (0..64).flat_map(|i| [colors[i % 4][0], colors[i % 4][1], colors[i % 4][2], 255]).collect();
+        for method in ["median-cut", "kmeans", "octree"] {
+            let bytes = extract_palette(method, &data, 4, 1).unwrap().to_rgb_bytes();
+            let mut extracted: Vec<&[u8]> = bytes.chunks_exact(3).collect();
+            extracted.sort();
+            let mut expected: Vec<&[u8]> = colors.iter().map(|c| &c[..]).collect();
+            expected.sort();
+            assert_eq!(extracted, expected, "{method}");
+        }
+    }
+}
@@ -1,4 +1,4 @@
-import init, { apply_dither } from './pkg/dither_wasm.js';
+import init, { apply_dither, DitherOptions } from './pkg/dither_wasm.js';
 
 const MAX_FILE_SIZE = 20 * 1024 * 1024;          // 20MB limit
 const OUTPUT_SIZE_THRESHOLD = 4000000;             // 4,000,000 pixels (for some output functions)
@@ -252,28 +252,12 @@ document.addEventListener('DOMContentLoaded', function() {
     function updateLogoPreview(algorithm) {
       ctx.drawImage(logoImg, 0, 0, logoCanvas.width, logoCanvas.height);
       let imageData = ctx.getImageData(0, 0, logoCanvas.width, logoCanvas.height);
-      const threshold = 100;
-      const contrast = 200;
-      const gamma = 1;
-      const pixelation = 0;
-      const blur = 0;
-      const blockScale = 9;
-      const bayerWidth = 4;
-      const bayerHeight = 4;
-      apply_dither(
-        algorithm,
-        imageData.data,
-        imageData.width,
-        imageData.height,
-        threshold,
-        contrast,
-        gamma,
-        pixelation,
-        blur,
-        blockScale,
-        bayerWidth,
-        bayerHeight
-      );
+      const options = new DitherOptions();
+      options.threshold = 100;
+      options.contrast = 200;
+      options.block_scale = 9;
+      apply_dither(algorithm, imageData.data, imageData.width, imageData.height, options);
+      options.free();
       ctx.putImageData(imageData, 0, 0);
     }
     document.querySelectorAll('.custom-option').forEach(function(option) {
@@ -699,13 +683,28 @@ function updateSliderAvailability() {
   const thresholdSlider = document.getElementById('threshold');
   const scaleSlider = document.getElementById('block-scale');
   thresholdSlider.disabled = (algorithm === 'bayer' ||
+                              algorithm === 'bayer-recursive' ||
+                              algorithm === 'knoll' ||
+                              algorithm === 'yliluoma' ||
+                              algorithm === 'blue-noise' ||
+                              algorithm === 'white-noise' ||
+                              algorithm === 'triangular-noise' ||
+                              algorithm === 'interleaved-gradient-noise' ||
+                              algorithm === 'halftone' ||
+                              algorithm === 'cmyk-halftone' ||
+                              algorithm === 'line-screen' ||
+                              algorithm === 'wavy-lines' ||
+                              algorithm === 'concentric-circles' ||
+                              algorithm === 'crosshatch' ||
+                              algorithm === 'stipple' ||
                               algorithm === 'sierra' ||
                               algorithm === 'sierra-two-row' ||
                               algorithm === 'sierra-lite');
   scaleSlider.disabled = (algorithm === 'threshold');
   const bayerControls = document.querySelector('.bayer-options');
   if (bayerControls) {
-    bayerControls.style.display = (algorithm === 'bayer') ? 'block' : 'none';
+    bayerControls.style.display = (algorithm === 'bayer' || algorithm === 'bayer-recursive' ||
+                                   algorithm === 'knoll' || algorithm === 'yliluoma') ? 'block' : 'none';
   }
 }
 
@@ -797,38 +796,21 @@ function updateCanvas() {
   const blur = parseInt(document.getElementById('blur').value);
   const algorithm = document.getElementById('algorithm').value;
   const blockScale = parseInt(document.getElementById('block-scale').value) || 1;
-  
-  if (algorithm === 'bayer') {
-    const bayerWidth = parseInt(document.querySelector('#custom-bayer-width .number-display').textContent) || 4;
-    const bayerHeight = parseInt(document.querySelector('#custom-bayer-height .number-display').textContent) || 4;
-    apply_dither(
-      algorithm,
-      imageData.data,
-      imageData.width,
-      imageData.height,
-      threshold,
-      contrast,
-      gamma,
-      pixelation,
-      blur,
-      blockScale,
-      bayerWidth,
-      bayerHeight
-    );
-  } else {
-    apply_dither(
-      algorithm,
-      imageData.data,
-      imageData.width,
-      imageData.height,
-      threshold,
-      contrast,
-      gamma,
-      pixelation,
-      blur,
-      blockScale
-    );
+
+  const options = new DitherOptions();
+  options.threshold = threshold;
+  options.contrast = contrast;
+  options.gamma = gamma;
+  options.pixelation = pixelation;
+  options.blur = blur;
+  options.block_scale = blockScale;
+  if (algorithm === 'bayer' || algorithm === 'bayer-recursive' ||
+      algorithm === 'knoll' || algorithm === 'yliluoma') {
+    options.bayer_width = parseInt(document.querySelector('#custom-bayer-width .number-display').textContent) || 4;
+    options.bayer_height = parseInt(document.querySelector('#custom-bayer-height .number-display').textContent) || 4;
   }
+  apply_dither(algorithm, imageData.data, imageData.width, imageData.height, options);
+  options.free();
   if (invertImage) {
     for (let i = 0; i < imageData.data.length; i += 4) {
       imageData.data[i] = 255 - imageData.data[i];